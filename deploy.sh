@@ -9,6 +9,9 @@ URL=sudoku-solver
 trunk build --release --public-url $URL
 cp $SRC_DIR/index.html $SRC_DIR/404.html
 cp $SRC_DIR/index.html $SRC_DIR/16.html
+cp $SRC_DIR/index.html $SRC_DIR/samurai.html
+cp $SRC_DIR/index.html $SRC_DIR/butterfly.html
+cp $SRC_DIR/index.html $SRC_DIR/twodoku.html
 
 mkdir -p $TMP_DIR
 cd $TMP_DIR
@@ -0,0 +1,7 @@
+use yew_agent::Threaded;
+
+use sudoku::workers::MultiMinimizingWorker;
+
+fn main() {
+    MultiMinimizingWorker::<9>::register();
+}
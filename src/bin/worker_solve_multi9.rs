@@ -0,0 +1,7 @@
+use yew_agent::Threaded;
+
+use sudoku::workers::MultiSolvingWorker;
+
+fn main() {
+    MultiSolvingWorker::<9>::register();
+}
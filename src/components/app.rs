@@ -1,21 +1,32 @@
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::components::multi_sudoku_solver::MultiSudokuSolver;
 use crate::components::sudoku_solver::SudokuSolver;
+use crate::solver::multi::MultiLayout;
 
 #[derive(Clone, Routable, PartialEq)]
-enum Route {
+pub enum Route {
     #[not_found]
     #[at("/")]
     Normal,
     #[at("/16")]
     Big,
+    #[at("/samurai")]
+    Samurai,
+    #[at("/butterfly")]
+    Butterfly,
+    #[at("/twodoku")]
+    Twodoku,
 }
 
 fn switch(routes: &Route) -> Html {
     match routes {
         Route::Normal => html! { <SudokuSolver<9> /> },
         Route::Big => html! { <SudokuSolver<16> /> },
+        Route::Samurai => html! { <MultiSudokuSolver<9> layout={MultiLayout::samurai()} /> },
+        Route::Butterfly => html! { <MultiSudokuSolver<9> layout={MultiLayout::butterfly()} /> },
+        Route::Twodoku => html! { <MultiSudokuSolver<9> layout={MultiLayout::twodoku()} /> },
     }
 }
 
@@ -1,3 +1,6 @@
 pub mod app;
+pub mod multi_sudoku_solver;
+pub mod puzzle_library;
+pub mod solver_controls;
 pub mod sudoku_input;
 pub mod sudoku_solver;
@@ -0,0 +1,406 @@
+use web_sys::HtmlElement;
+use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
+
+use crate::components::solver_controls::{NumberButtons, SolverControls};
+use crate::components::sudoku_input::key_value;
+use crate::solver::multi::{MultiDomains, MultiLayout, MultiSudoku};
+use crate::solver::sudoku::Cell;
+use crate::workers::{MultiMinimizingWorker, MultiSolvingWorker};
+
+pub enum MultiSolverMessage {
+    Select(Option<Cell>),
+    Key(KeyboardEvent),
+    Put(Option<u32>),
+    Solve,
+    Clear,
+    Solved(Option<MultiSudoku>, usize),
+    Minimized(MultiDomains, MultiDomains, usize),
+    Undo,
+    Redo,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props<const N: usize> {
+    pub layout: MultiLayout<N>,
+}
+
+#[derive(Clone)]
+struct MultiHistoryItem {
+    sudoku: MultiSudoku,
+    domains: MultiDomains,
+    unsure: MultiDomains,
+    change: usize,
+    solved: Option<bool>,
+}
+
+impl MultiHistoryItem {
+    fn new<const N: usize>(layout: &MultiLayout<N>, sudoku: MultiSudoku) -> Self {
+        let problem = layout.create_problem(&layout.sudoku_domains(&sudoku));
+        let domains = layout.reshape_domains(problem.reduced_domains());
+        MultiHistoryItem {
+            sudoku,
+            unsure: domains.clone(),
+            domains,
+            change: 0,
+            solved: None,
+        }
+    }
+}
+
+pub struct MultiSudokuSolver<const N: usize> {
+    layout: MultiLayout<N>,
+    history: Vec<MultiHistoryItem>,
+    hist_pos: usize,
+    change: usize,
+    solving: Option<usize>,
+    minimizing: Option<usize>,
+    selected: Option<Cell>,
+    last: Option<Cell>,
+    cells: Vec<Vec<NodeRef>>,
+    solver_bridge: Box<dyn Bridge<MultiSolvingWorker<N>>>,
+    minimize_bridge: Box<dyn Bridge<MultiMinimizingWorker<N>>>,
+}
+
+fn box_borders<const N: usize>(layout: &MultiLayout<N>, (row, col): Cell) -> Vec<&'static str> {
+    let sr = layout.box_size();
+    let mut res = Vec::with_capacity(4);
+    for (r, c) in layout.grids_at((row, col)) {
+        let (i, j) = (row - r, col - c);
+        if i % sr == 0 {
+            res.push("sudoku-cell-box-top");
+        }
+        if i % sr == sr - 1 {
+            res.push("sudoku-cell-box-bottom");
+        }
+        if j % sr == 0 {
+            res.push("sudoku-cell-box-left");
+        }
+        if j % sr == sr - 1 {
+            res.push("sudoku-cell-box-right");
+        }
+    }
+    res.sort();
+    res.dedup();
+    return res;
+}
+
+fn next_cell<const N: usize>(
+    layout: &MultiLayout<N>,
+    (row, col): Cell,
+    event: &KeyboardEvent,
+) -> Option<Cell> {
+    let (dr, dc) = match event.key_code() {
+        38 => (-1, 0),
+        40 => (1, 0),
+        37 => (0, -1),
+        39 => (0, 1),
+        _ => return None,
+    };
+    let (mut r, mut c) = (row as isize + dr, col as isize + dc);
+    while r >= 0 && c >= 0 && (r as usize) < layout.height() && (c as usize) < layout.width() {
+        if layout.contains((r as usize, c as usize)) {
+            return Some((r as usize, c as usize));
+        }
+        r += dr;
+        c += dc;
+    }
+    return None;
+}
+
+impl<const N: usize> MultiSudokuSolver<N> {
+    fn reset(&mut self, layout: MultiLayout<N>) {
+        self.change += 1;
+        let mut hist = MultiHistoryItem::new(&layout, layout.empty_sudoku());
+        hist.change = self.change;
+        self.history = vec![hist];
+        self.hist_pos = 0;
+        self.solving = None;
+        self.selected = None;
+        self.last = None;
+        self.cells = (0..layout.height())
+            .map(|_| (0..layout.width()).map(|_| NodeRef::default()).collect())
+            .collect();
+        self.layout = layout;
+    }
+
+    fn current_history(&self) -> &MultiHistoryItem {
+        &self.history[self.hist_pos]
+    }
+
+    fn has_no_solution(&self) -> bool {
+        if self.current_history().solved == Some(false) {
+            return true;
+        }
+        for i in 0..self.layout.height() {
+            for j in 0..self.layout.width() {
+                if self.layout.contains((i, j)) && self.current_history().domains[i][j].is_empty() {
+                    return true;
+                }
+            }
+        }
+        return false;
+    }
+
+    fn has_multiple_solution(&self) -> bool {
+        let MultiHistoryItem {
+            domains, unsure, ..
+        } = self.current_history();
+        for (&d1, &d2) in domains.iter().flatten().zip(unsure.iter().flatten()) {
+            let sure = d1.without_all(d2);
+            if !sure.is_empty() && !sure.is_singleton() {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    fn start_domain_compute(&mut self) {
+        if self.minimizing == None
+            && self
+                .current_history()
+                .unsure
+                .iter()
+                .flatten()
+                .any(|x| !x.is_empty())
+        {
+            self.minimizing = Some(self.current_history().change);
+            self.minimize_bridge.send((
+                self.layout.clone(),
+                self.current_history().domains.clone(),
+                self.current_history().unsure.clone(),
+                self.current_history().change,
+            ));
+        }
+    }
+
+    fn history_push_sudoku(&mut self, sudoku: MultiSudoku) {
+        if sudoku != self.current_history().sudoku {
+            self.change += 1;
+            if self.hist_pos < self.history.len() - 1 {
+                self.history.resize_with(self.hist_pos + 1, || panic!());
+            }
+            let mut hist = MultiHistoryItem::new(&self.layout, sudoku);
+            hist.change = self.change;
+            self.history.push(hist);
+            self.hist_pos += 1;
+        }
+    }
+
+    fn put(&mut self, (r, c): Cell, put: Option<u32>) {
+        if self.solving == None {
+            let mut sudoku = self.current_history().sudoku.clone();
+            sudoku[r][c] = put;
+            self.history_push_sudoku(sudoku);
+            self.start_domain_compute();
+        }
+    }
+}
+
+impl<const N: usize> Component for MultiSudokuSolver<N> {
+    type Message = MultiSolverMessage;
+    type Properties = Props<N>;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut res = Self {
+            layout: ctx.props().layout.clone(),
+            history: Vec::new(),
+            hist_pos: 0,
+            change: 0,
+            solving: None,
+            minimizing: None,
+            selected: None,
+            last: None,
+            cells: Vec::new(),
+            solver_bridge: MultiSolvingWorker::bridge(
+                ctx.link()
+                    .callback(|(sol, id)| Self::Message::Solved(sol, id)),
+            ),
+            minimize_bridge: MultiMinimizingWorker::bridge(
+                ctx.link()
+                    .callback(|(sol, uns, id)| Self::Message::Minimized(sol, uns, id)),
+            ),
+        };
+        res.reset(ctx.props().layout.clone());
+        res.start_domain_compute();
+        return res;
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        if ctx.props().layout != self.layout {
+            self.reset(ctx.props().layout.clone());
+            self.start_domain_compute();
+        }
+        return true;
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Self::Message::Select(cell) => {
+                self.selected = cell;
+                if cell != None {
+                    self.last = cell;
+                }
+            }
+            Self::Message::Key(event) => {
+                if let Some(cell) = self.selected {
+                    if let Some((r, c)) = next_cell(&self.layout, cell, &event) {
+                        if let Some(elem) = self.cells[r][c].cast::<HtmlElement>() {
+                            _ = elem.focus();
+                        }
+                    } else if let Some(put) = key_value::<N>(&event) {
+                        self.put(cell, put);
+                    }
+                }
+            }
+            Self::Message::Put(put) => {
+                if let Some(cell) = self.last {
+                    self.put(cell, put);
+                }
+            }
+            Self::Message::Solve => {
+                if self.solving == None {
+                    self.solving = Some(self.change);
+                    self.solver_bridge.send((
+                        self.layout.clone(),
+                        self.layout.sudoku_domains(&self.current_history().sudoku),
+                        self.change,
+                    ));
+                }
+            }
+            Self::Message::Clear => {
+                if self.solving == None {
+                    self.reset(self.layout.clone());
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::Solved(res, id) => {
+                if self.solving == Some(id) {
+                    self.solving = None;
+                    if let Some(sol) = res {
+                        self.history_push_sudoku(sol);
+                        self.history[self.hist_pos].solved = Some(true);
+                    } else {
+                        self.history[self.hist_pos].solved = Some(false);
+                    }
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::Minimized(sol, uns, id) => {
+                if self.minimizing == Some(id) {
+                    self.minimizing = None;
+                    for hist in self.history.iter_mut().rev() {
+                        if hist.change == id {
+                            hist.domains = sol;
+                            hist.unsure = uns;
+                            break;
+                        }
+                    }
+                }
+                self.start_domain_compute();
+            }
+            Self::Message::Undo => {
+                if self.hist_pos != 0 {
+                    self.hist_pos -= 1;
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::Redo => {
+                if self.hist_pos != self.history.len() - 1 {
+                    self.hist_pos += 1;
+                    self.start_domain_compute();
+                }
+            }
+        }
+        return true;
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let layout = &self.layout;
+        let MultiHistoryItem {
+            sudoku,
+            domains,
+            unsure,
+            ..
+        } = self.current_history();
+        let mut grid_classes = Vec::with_capacity(3);
+        if self.solving != None {
+            grid_classes.push("sudoku-working");
+        }
+        let style = format!(
+            "grid-template-columns: repeat({}, 1fr); grid-template-rows: repeat({}, 1fr); aspect-ratio: {} / {};",
+            layout.width(),
+            layout.height(),
+            layout.width(),
+            layout.height(),
+        );
+        html! {
+            <div class="sudoku-solver">
+                <div class="sudoku-input-wrapper">
+                    <div class={classes!("sudoku-grid-wrapper", grid_classes)}>
+                        <div class="status-row">
+                            <SolverControls
+                                status={
+                                    if self.has_no_solution() {
+                                        "no solutions"
+                                    } else if self.has_multiple_solution() {
+                                        "multiple solutions"
+                                    } else {
+                                        ""
+                                    }
+                                }
+                                can_undo={self.hist_pos != 0}
+                                can_redo={self.hist_pos != self.history.len() - 1}
+                                can_solve={self.solving == None && self.current_history().solved == None}
+                                on_undo={ctx.link().callback(|_| Self::Message::Undo)}
+                                on_redo={ctx.link().callback(|_| Self::Message::Redo)}
+                                on_solve={ctx.link().callback(|_| Self::Message::Solve)}
+                                on_clear={ctx.link().callback(|_| Self::Message::Clear)}
+                            />
+                        </div>
+                        <div
+                            class="sudoku-grid sudoku-grid-multi"
+                            {style}
+                            onkeydown={ctx.link().callback(Self::Message::Key)}
+                            onblur={ctx.link().callback(|_| Self::Message::Select(None))}
+                        >
+                            { (0..layout.height()).map(|r|
+                                (0..layout.width()).map(|c| {
+                                    if !layout.contains((r, c)) {
+                                        return html! { <div class="sudoku-cell-gap" /> };
+                                    }
+                                    let mut cell_classes = box_borders(layout, (r, c));
+                                    if self.selected == Some((r, c)) {
+                                        cell_classes.push("sudoku-cell-selected");
+                                    }
+                                    html! {
+                                        <div
+                                            class={classes!("sudoku-cell", sudoku[r][c].map(|_| "sudoku-cell-set"), cell_classes)}
+                                            onfocus={ctx.link().callback(move |_| Self::Message::Select(Some((r, c))))}
+                                        >
+                                            <div class={classes!("sudoku-cell-result", format!("sudoku-results-{}", domains[r][c].len()))}>
+                                                { domains[r][c].map(|e| html!{
+                                                    <div class={classes!(
+                                                        if unsure[r][c].contains(e) {
+                                                            "sudoku-result-unsure"
+                                                        } else {
+                                                            "sudoku-result-sure"
+                                                        }
+                                                    )}>{e + 1}</div>
+                                                }).collect::<Html>() }
+                                            </div>
+                                            <div class="sudoku-cell-input" tabindex="0" ref={self.cells[r][c].clone()}>{
+                                                sudoku[r][c].map(|v| v.to_string()).unwrap_or_default()
+                                            }</div>
+                                        </div>
+                                    }
+                                }).collect::<Html>()
+                            ).collect::<Html>() }
+                        </div>
+                    </div>
+                    <NumberButtons<N> on_put={ctx.link().callback(Self::Message::Put)} />
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::components::app::Route;
+use crate::components::sudoku_solver::local_storage;
+use crate::solver::links::{decode_puzzle, encode_puzzle};
+use crate::solver::sudoku::{parse_sudoku_list, Puzzle};
+
+/// The most puzzles that are taken from a single file, to stay within the storage limits.
+const MAX_IMPORT: usize = 1000;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub name: String,
+    pub source: String,
+    /// The day the puzzle was added, as `YYYY-MM-DD`.
+    pub date: String,
+    pub difficulty: String,
+    pub solved: bool,
+    /// The puzzle, encoded as in the links of the solver.
+    pub puzzle: String,
+}
+
+fn today() -> String {
+    let date = String::from(js_sys::Date::new_0().to_iso_string());
+    return date.chars().take(10).collect();
+}
+
+fn load_library(key: &str) -> Vec<LibraryEntry> {
+    return local_storage()
+        .and_then(|storage| storage.get_item(key).ok()?)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+}
+
+fn save_library(key: &str, entries: &[LibraryEntry]) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(entries)) {
+        let _ = storage.set_item(key, &json);
+    }
+}
+
+/// Whether the grid has been filled in starting from the given puzzle.
+fn is_solved_from<const N: usize>(puzzle: &Puzzle<N>, grid: &Puzzle<N>) -> bool {
+    if puzzle.restrictions != grid.restrictions
+        || puzzle.regions != grid.regions
+        || puzzle.variants != grid.variants
+    {
+        return false;
+    }
+    for i in 0..N {
+        for j in 0..N {
+            if puzzle.sudoku[i][j].is_some() && puzzle.sudoku[i][j] != grid.sudoku[i][j] {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props<const N: usize> {
+    pub puzzle: Puzzle<N>,
+    #[prop_or(false)]
+    pub solved: bool,
+    pub on_load: Callback<Puzzle<N>>,
+}
+
+#[function_component(PuzzleLibrary)]
+pub fn puzzle_library<const N: usize>(props: &Props<N>) -> Html {
+    let Props {
+        puzzle,
+        solved,
+        on_load,
+    } = props;
+    let key =
+        use_route::<Route>().map(|route| format!("sudoku-solver-library:{}", route.to_path()));
+    let entries = {
+        let key = key.clone();
+        use_state(move || key.map(|key| load_library(&key)).unwrap_or_default())
+    };
+    let name = use_state(String::new);
+    let difficulty = use_state(String::new);
+    let message = use_state(String::new);
+    let set_entries = {
+        let key = key.clone();
+        let entries = entries.clone();
+        Callback::from(move |new: Vec<LibraryEntry>| {
+            if let Some(key) = &key {
+                save_library(key, &new);
+            }
+            entries.set(new);
+        })
+    };
+    {
+        let entries = entries.clone();
+        let set_entries = set_entries.clone();
+        let puzzle = puzzle.clone();
+        use_effect_with_deps(
+            move |&solved| {
+                if solved {
+                    let mut new = (*entries).clone();
+                    for entry in new.iter_mut().filter(|entry| !entry.solved) {
+                        entry.solved = matches!(
+                            decode_puzzle::<N>(&entry.puzzle),
+                            Ok(start) if is_solved_from(&start, &puzzle)
+                        );
+                    }
+                    if new != *entries {
+                        set_entries.emit(new);
+                    }
+                }
+                || ()
+            },
+            *solved,
+        );
+    }
+    let onname = {
+        let name = name.clone();
+        Callback::from(move |e: InputEvent| {
+            name.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    let ondifficulty = {
+        let difficulty = difficulty.clone();
+        Callback::from(move |e: InputEvent| {
+            difficulty.set(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+    let onsave = {
+        let entries = entries.clone();
+        let set_entries = set_entries.clone();
+        let name = name.clone();
+        let difficulty = difficulty.clone();
+        let puzzle = puzzle.clone();
+        let solved = *solved;
+        Callback::from(move |_| {
+            let mut new = (*entries).clone();
+            new.push(LibraryEntry {
+                name: if name.trim().is_empty() {
+                    format!("Puzzle {}", new.len() + 1)
+                } else {
+                    name.trim().to_owned()
+                },
+                source: "solver".to_owned(),
+                date: today(),
+                difficulty: difficulty.trim().to_owned(),
+                solved,
+                puzzle: encode_puzzle(&puzzle),
+            });
+            set_entries.emit(new);
+            name.set(String::new());
+            difficulty.set(String::new());
+        })
+    };
+    let onimport = {
+        let key = key.clone();
+        let entries = entries.clone();
+        let set_entries = set_entries.clone();
+        let message = message.clone();
+        Callback::from(move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                let key = key.clone();
+                let entries = entries.clone();
+                let set_entries = set_entries.clone();
+                let message = message.clone();
+                spawn_local(async move {
+                    let text = JsFuture::from(file.text()).await.ok();
+                    let puzzles = text
+                        .and_then(|text| text.as_string())
+                        .map(|text| parse_sudoku_list::<N>(&text))
+                        .unwrap_or_default();
+                    let source = file.name();
+                    let stem = source
+                        .rsplit_once('.')
+                        .map_or(source.as_str(), |(stem, _)| stem);
+                    // The library may have changed while the file was read, and the stored
+                    // entries are kept up to date by `set_entries`.
+                    let mut new = match &key {
+                        Some(key) => load_library(key),
+                        None => (*entries).clone(),
+                    };
+                    for (k, (sudoku, rating)) in puzzles.iter().take(MAX_IMPORT).enumerate() {
+                        let mut puzzle = Puzzle::empty();
+                        puzzle.sudoku = *sudoku;
+                        new.push(LibraryEntry {
+                            name: format!("{} {}", stem, k + 1),
+                            source: source.clone(),
+                            date: today(),
+                            difficulty: rating.clone(),
+                            solved: false,
+                            puzzle: encode_puzzle(&puzzle),
+                        });
+                    }
+                    if puzzles.is_empty() {
+                        message.set(format!("no puzzles found in {}", source));
+                    } else if puzzles.len() > MAX_IMPORT {
+                        message.set(format!(
+                            "imported the first {} of {} puzzles",
+                            MAX_IMPORT,
+                            puzzles.len()
+                        ));
+                    } else {
+                        message.set(format!("imported {} puzzles", puzzles.len()));
+                    }
+                    set_entries.emit(new);
+                });
+            }
+            input.set_value("");
+        })
+    };
+    let onload = |i: usize| {
+        let entries = entries.clone();
+        let on_load = on_load.clone();
+        Callback::from(move |_| {
+            if let Some(puzzle) = entries
+                .get(i)
+                .and_then(|e| decode_puzzle::<N>(&e.puzzle).ok())
+            {
+                on_load.emit(puzzle);
+            }
+        })
+    };
+    let ondelete = |i: usize| {
+        let entries = entries.clone();
+        let set_entries = set_entries.clone();
+        Callback::from(move |_| {
+            let mut new = (*entries).clone();
+            new.remove(i);
+            set_entries.emit(new);
+        })
+    };
+    html! {
+        <div class="puzzle-library">
+            <div class="library-save">
+                <input placeholder="Name" value={(*name).clone()} oninput={onname} />
+                <input placeholder="Difficulty" value={(*difficulty).clone()} oninput={ondifficulty} />
+                <button onclick={onsave} disabled={*puzzle == Puzzle::empty()}>{"Save"}</button>
+                <label class="library-import">
+                    {"Import file"}
+                    <input type="file" accept=".txt,.sdk" onchange={onimport} />
+                </label>
+            </div>
+            <div class="info-text">{ (*message).clone() }</div>
+            <table class="library-entries">
+                { for entries.iter().enumerate().map(|(i, entry)| html! {
+                    <tr class={classes!(entry.solved.then_some("library-solved"))}>
+                        <td>{ entry.name.clone() }</td>
+                        <td>{ entry.source.clone() }</td>
+                        <td>{ entry.date.clone() }</td>
+                        <td>{ entry.difficulty.clone() }</td>
+                        <td>{ if entry.solved { "solved" } else { "" } }</td>
+                        <td><button onclick={onload(i)}>{"Load"}</button></td>
+                        <td><button onclick={ondelete(i)}>{"Delete"}</button></td>
+                    </tr>
+                }) }
+            </table>
+        </div>
+    }
+}
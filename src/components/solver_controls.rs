@@ -0,0 +1,66 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ControlsProps {
+    pub status: &'static str,
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub can_solve: bool,
+    pub on_undo: Callback<MouseEvent>,
+    pub on_redo: Callback<MouseEvent>,
+    pub on_solve: Callback<MouseEvent>,
+    pub on_clear: Callback<MouseEvent>,
+}
+
+/// The status of the current puzzle and the buttons for the history and the solver.
+#[function_component(SolverControls)]
+pub fn solver_controls(props: &ControlsProps) -> Html {
+    let ControlsProps {
+        status,
+        can_undo,
+        can_redo,
+        can_solve,
+        on_undo,
+        on_redo,
+        on_solve,
+        on_clear,
+    } = props;
+    html! {
+        <>
+            <div class="info-text">{ *status }</div>
+            <div class="buttons">
+                <button onclick={on_undo} disabled={!can_undo}>
+                    <svg xmlns="http://www.w3.org/2000/svg" height="24px" viewBox="0 0 24 24" width="24px"><path d="M0 0h24v24H0z" fill="none"/><path d="M12.5 8c-2.65 0-5.05.99-6.9 2.6L2 7v9h9l-3.62-3.62c1.39-1.16 3.16-1.88 5.12-1.88 3.54 0 6.55 2.31 7.6 5.5l2.37-.78C21.08 11.03 17.15 8 12.5 8z"/></svg>
+                </button>
+                <button onclick={on_redo} disabled={!can_redo}>
+                    <svg xmlns="http://www.w3.org/2000/svg" height="24px" viewBox="0 0 24 24" width="24px"><path d="M0 0h24v24H0z" fill="none"/><path d="M18.4 10.6C16.55 8.99 14.15 8 11.5 8c-4.65 0-8.58 3.03-9.96 7.22L3.9 16c1.05-3.19 4.05-5.5 7.6-5.5 1.95 0 3.73.72 5.12 1.88L13 16h9V7l-3.6 3.6z"/></svg>
+                </button>
+                <button onclick={on_solve} disabled={!can_solve}>{"Solve"}</button>
+                <button onclick={on_clear}>{"Clear"}</button>
+            </div>
+        </>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct NumberProps {
+    pub on_put: Callback<Option<u32>>,
+}
+
+/// A button for every digit of the grid, and one for clearing the cell.
+#[function_component(NumberButtons)]
+pub fn number_buttons<const N: usize>(props: &NumberProps) -> Html {
+    html! {
+        <div class={classes!("sudoku-input", format!("sudoku-input-{N}"))}>
+            { (0..=N as u32).map(|n| {
+                let on_put = props.on_put.clone();
+                html! {
+                    <button
+                        class={classes!("number-button", format!("number-button-{}", n))}
+                        onclick={Callback::from(move |_| on_put.emit(if n == 0 { None } else { Some(n) }))}
+                    >{ if n == 0 { "_".to_owned() } else { format!("{:X}", n & 0xf) } }</button>
+                }
+            }).collect::<Html>() }
+        </div>
+    }
+}
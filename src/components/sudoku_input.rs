@@ -1,7 +1,14 @@
-use web_sys::HtmlElement;
+use wasm_bindgen::JsCast;
+use web_sys::{DataTransfer, HtmlElement, HtmlInputElement};
 use yew::{prelude::*, Children, Properties};
 
-use crate::solver::sudoku::{empty_domains, Sudoku, SudokuDomains};
+use crate::components::solver_controls::NumberButtons;
+use crate::solver::domain::DomainSet;
+use crate::solver::sudoku::{
+    center_dot_region, default_domains, default_regions, digits_where, empty_domains,
+    extra_regions, invalid_regions, parse_sudoku, windoku_regions, Cell, DotKind, Line, LineKind,
+    Side, Sudoku, SudokuDomains, SudokuRegions, Variant,
+};
 
 fn change_sudoku<const N: usize>(
     mut sudoku: Sudoku<N>,
@@ -13,25 +20,519 @@ fn change_sudoku<const N: usize>(
     return sudoku;
 }
 
+pub(crate) fn key_value<const N: usize>(event: &KeyboardEvent) -> Option<Option<u32>> {
+    let key = event.key_code();
+    if key >= ('1' as u32) && key <= ('9' as u32) && key - ('0' as u32) <= N as u32 {
+        return Some(Some(key - ('0' as u32)));
+    } else if key >= ('A' as u32) && key <= ('F' as u32) && 10 + key - ('A' as u32) <= N as u32 {
+        return Some(Some(10 + key - ('A' as u32)));
+    } else if key >= ('0' as u32) && 16 <= N {
+        return Some(Some(16));
+    } else if key == (' ' as u32) || key == 8 {
+        return Some(None);
+    }
+    return None;
+}
+
 fn sudoku_change<const N: usize>(
     mut sudoku: Sudoku<N>,
     row: usize,
     col: usize,
     event: &KeyboardEvent,
 ) -> Sudoku<N> {
-    let key = event.key_code();
-    if key >= ('1' as u32) && key <= ('9' as u32) && key - ('0' as u32) <= N as u32 {
-        sudoku[row][col] = Some(key - ('0' as u32));
-    } else if key >= ('A' as u32) && key <= ('F' as u32) && 10 + key - ('A' as u32) <= N as u32 {
-        sudoku[row][col] = Some(10 + key - ('A' as u32));
-    } else if key >= ('0' as u32) && 16 <= N {
-        sudoku[row][col] = Some(16);
-    } else if key == (' ' as u32) || key == 8 {
-        sudoku[row][col] = None;
+    if let Some(put) = key_value::<N>(event) {
+        sudoku[row][col] = put;
     }
     return sudoku;
 }
 
+fn change_restriction<const N: usize>(
+    mut restrictions: SudokuDomains<N>,
+    row: usize,
+    col: usize,
+    toggle: Option<u32>,
+) -> SudokuDomains<N> {
+    if let Some(v) = toggle {
+        if restrictions[row][col].contains(v - 1) {
+            restrictions[row][col].remove(v - 1);
+        } else {
+            restrictions[row][col].add(v - 1);
+        }
+    }
+    if toggle.is_none() || restrictions[row][col].is_empty() {
+        restrictions[row][col] = DomainSet::range(0..N as u32);
+    }
+    return restrictions;
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EditMode {
+    Digits,
+    Restrict,
+    Regions,
+    Cages,
+    Draw,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Restriction {
+    Even,
+    Odd,
+    Low,
+    High,
+}
+
+impl Restriction {
+    const ALL: [Restriction; 4] = [
+        Restriction::Even,
+        Restriction::Odd,
+        Restriction::Low,
+        Restriction::High,
+    ];
+
+    fn digits<const N: usize>(self) -> DomainSet {
+        match self {
+            Restriction::Even => digits_where::<N>(|d| d % 2 == 0),
+            Restriction::Odd => digits_where::<N>(|d| d % 2 == 1),
+            Restriction::Low => digits_where::<N>(|d| d <= N as u32 / 2),
+            Restriction::High => digits_where::<N>(|d| d > (N as u32).div_ceil(2)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Restriction::Even => "even",
+            Restriction::Odd => "odd",
+            Restriction::Low => "low",
+            Restriction::High => "high",
+        }
+    }
+}
+
+fn restriction_class<const N: usize>(domain: DomainSet) -> Option<String> {
+    if domain == DomainSet::range(0..N as u32) {
+        return None;
+    }
+    for restriction in Restriction::ALL {
+        if domain == restriction.digits::<N>() {
+            return Some(format!("sudoku-cell-{}", restriction.name()));
+        }
+    }
+    return Some("sudoku-cell-restricted".to_owned());
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClueKind {
+    Sandwich,
+    LittleKiller(bool),
+    Skyscraper,
+}
+
+fn diagonal_arrow(side: Side, forward: bool) -> &'static str {
+    match (side, forward) {
+        (Side::Top, true) | (Side::Left, true) => "↘",
+        (Side::Top, false) | (Side::Right, true) => "↙",
+        (Side::Bottom, true) | (Side::Left, false) => "↗",
+        (Side::Bottom, false) | (Side::Right, false) => "↖",
+    }
+}
+
+fn clue_change(
+    mut variants: Vec<Variant>,
+    kind: ClueKind,
+    side: Side,
+    index: usize,
+    value: &str,
+) -> Vec<Variant> {
+    variants.retain(|v| v.outside_clue() != Some((side, index)));
+    let digits = value
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>();
+    if let Ok(total) = digits.parse() {
+        variants.push(match kind {
+            ClueKind::Sandwich => Variant::Sandwich { side, index, total },
+            ClueKind::LittleKiller(forward) => Variant::LittleKiller {
+                side,
+                index,
+                forward,
+                total,
+            },
+            ClueKind::Skyscraper => Variant::Skyscraper {
+                side,
+                index,
+                count: total as usize,
+            },
+        });
+    }
+    return variants;
+}
+
+fn clue_text(variants: &[Variant], side: Side, index: usize) -> (String, Option<&'static str>) {
+    for variant in variants {
+        if variant.outside_clue() == Some((side, index)) {
+            match variant {
+                Variant::Sandwich { total, .. } => {
+                    return (total.to_string(), Some("sudoku-clue-sandwich"));
+                }
+                Variant::LittleKiller { forward, total, .. } => {
+                    let text = format!("{}{}", total, diagonal_arrow(side, *forward));
+                    return (text, Some("sudoku-clue-little-killer"));
+                }
+                Variant::Skyscraper { count, .. } => {
+                    return (count.to_string(), Some("sudoku-clue-skyscraper"));
+                }
+                _ => {}
+            }
+        }
+    }
+    return ("".to_owned(), None);
+}
+
+fn toggle_variant(mut variants: Vec<Variant>, variant: Variant) -> Vec<Variant> {
+    if variants.contains(&variant) {
+        variants.retain(|v| *v != variant);
+    } else {
+        variants.push(variant);
+    }
+    return variants;
+}
+
+fn region_classes<const N: usize>(variants: &[Variant], cell: Cell) -> Vec<&'static str> {
+    let mut classes = Vec::new();
+    for variant in variants {
+        let shaded = match variant {
+            Variant::ExtraRegion(cells) => cells.contains(&cell),
+            Variant::Windoku => windoku_regions::<N>().iter().any(|r| r.contains(&cell)),
+            _ => false,
+        };
+        if shaded {
+            classes.push("sudoku-cell-extra-region");
+        }
+        if *variant == Variant::CenterDot && center_dot_region::<N>().contains(&cell) {
+            classes.push("sudoku-cell-center-dot");
+        }
+    }
+    return classes;
+}
+
+/// Moves all painted cells into the region of the cell where painting started.
+fn paint_region<const N: usize>(
+    mut regions: SudokuRegions<N>,
+    painted: &[Cell],
+) -> SudokuRegions<N> {
+    if let Some(&(r, c)) = painted.first() {
+        let region = regions[r][c];
+        for &(r, c) in painted {
+            regions[r][c] = region;
+        }
+    }
+    return regions;
+}
+
+fn region_borders<const N: usize>(
+    regions: &SudokuRegions<N>,
+    (row, col): Cell,
+) -> Vec<&'static str> {
+    let mut classes = Vec::with_capacity(2);
+    if col + 1 < N && regions[row][col + 1] != regions[row][col] {
+        classes.push("sudoku-cell-region-right");
+    }
+    if row + 1 < N && regions[row + 1][col] != regions[row][col] {
+        classes.push("sudoku-cell-region-bottom");
+    }
+    return classes;
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DrawTool {
+    Line(LineKind),
+    Arrow,
+    Dot(DotKind),
+    Erase,
+}
+
+impl DrawTool {
+    const ALL: [DrawTool; 9] = [
+        DrawTool::Line(LineKind::Thermometer),
+        DrawTool::Arrow,
+        DrawTool::Line(LineKind::GermanWhisper),
+        DrawTool::Line(LineKind::Renban),
+        DrawTool::Line(LineKind::Palindrome),
+        DrawTool::Line(LineKind::RegionSum),
+        DrawTool::Dot(DotKind::White),
+        DrawTool::Dot(DotKind::Black),
+        DrawTool::Erase,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            DrawTool::Line(LineKind::Thermometer) => "thermometer",
+            DrawTool::Line(LineKind::GermanWhisper) => "german whisper",
+            DrawTool::Line(LineKind::Renban) => "renban",
+            DrawTool::Line(LineKind::Palindrome) => "palindrome",
+            DrawTool::Line(LineKind::RegionSum) => "region sum",
+            DrawTool::Arrow => "arrow",
+            DrawTool::Dot(DotKind::White) => "white dot",
+            DrawTool::Dot(DotKind::Black) => "black dot",
+            DrawTool::Erase => "erase",
+        }
+    }
+}
+
+fn drawn_cells(variant: &Variant) -> Vec<Cell> {
+    match variant {
+        Variant::Line(line) => line.cells.clone(),
+        Variant::Arrow { circle, arrow } => circle.iter().chain(arrow).copied().collect(),
+        Variant::Dot { cells, .. } => vec![cells.0, cells.1],
+        _ => Vec::new(),
+    }
+}
+
+fn toggle_dot(mut variants: Vec<Variant>, kind: DotKind, (a, b): (Cell, Cell)) -> Vec<Variant> {
+    let mut existing = None;
+    variants.retain(|v| match v {
+        Variant::Dot { kind, cells } if *cells == (a, b) || *cells == (b, a) => {
+            existing = Some(*kind);
+            false
+        }
+        _ => true,
+    });
+    if existing != Some(kind) {
+        variants.push(Variant::Dot {
+            kind,
+            cells: (a, b),
+        });
+    }
+    return variants;
+}
+
+fn add_drawing(mut variants: Vec<Variant>, tool: DrawTool, path: Vec<Cell>) -> Vec<Variant> {
+    match tool {
+        DrawTool::Erase => {
+            variants.retain(|v| !drawn_cells(v).iter().any(|c| path.contains(c)));
+        }
+        DrawTool::Dot(kind) => {
+            for pair in path.windows(2) {
+                let ((r1, c1), (r2, c2)) = (pair[0], pair[1]);
+                if r1.abs_diff(r2) + c1.abs_diff(c2) == 1 {
+                    variants = toggle_dot(variants, kind, (pair[0], pair[1]));
+                }
+            }
+        }
+        _ if path.len() < 2 => {}
+        DrawTool::Line(kind) => variants.push(Variant::Line(Line { kind, cells: path })),
+        DrawTool::Arrow => variants.push(Variant::Arrow {
+            circle: vec![path[0]],
+            arrow: path[1..].to_vec(),
+        }),
+    }
+    return variants;
+}
+
+fn center((row, col): Cell) -> (f64, f64) {
+    (col as f64 + 0.5, row as f64 + 0.5)
+}
+
+fn polyline_points(points: impl Iterator<Item = (f64, f64)>) -> String {
+    points
+        .map(|(x, y)| format!("{},{}", x, y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn variant_overlay(variant: &Variant) -> Html {
+    match variant {
+        Variant::Line(Line { kind, cells }) => {
+            let class = format!("sudoku-line-{:?}", kind).to_lowercase();
+            let bulb = match (kind, cells.first()) {
+                (LineKind::Thermometer, Some(&first)) => {
+                    let (x, y) = center(first);
+                    html! { <circle class="sudoku-thermometer-bulb" cx={x.to_string()} cy={y.to_string()} r="0.3" /> }
+                }
+                _ => html! {},
+            };
+            html! {
+                <>
+                    { bulb }
+                    <polyline
+                        class={classes!("sudoku-line", class)}
+                        points={polyline_points(cells.iter().copied().map(center))}
+                    />
+                </>
+            }
+        }
+        Variant::Arrow { circle, arrow } => {
+            // The shaft starts at the border of the circle instead of its center.
+            let start = match (circle.last(), arrow.first()) {
+                (Some(&from), Some(&to)) => {
+                    let ((x1, y1), (x2, y2)) = (center(from), center(to));
+                    let len = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+                    Some((x1 + 0.4 * (x2 - x1) / len, y1 + 0.4 * (y2 - y1) / len))
+                }
+                _ => None,
+            };
+            html! {
+                <>
+                    { circle.iter().map(|&cell| {
+                        let (x, y) = center(cell);
+                        html! { <circle class="sudoku-arrow-circle" cx={x.to_string()} cy={y.to_string()} r="0.4" /> }
+                    }).collect::<Html>() }
+                    <polyline
+                        class="sudoku-arrow"
+                        points={polyline_points(start.into_iter().chain(arrow.iter().copied().map(center)))}
+                        marker-end="url(#sudoku-arrowhead)"
+                    />
+                </>
+            }
+        }
+        Variant::Dot { kind, cells } => {
+            let ((x1, y1), (x2, y2)) = (center(cells.0), center(cells.1));
+            let class = format!("sudoku-dot-{:?}", kind).to_lowercase();
+            html! {
+                <circle
+                    class={classes!("sudoku-dot", class)}
+                    cx={((x1 + x2) / 2.0).to_string()}
+                    cy={((y1 + y2) / 2.0).to_string()}
+                    r="0.12"
+                />
+            }
+        }
+        _ => html! {},
+    }
+}
+
+/// The drawn variants and the path that is currently being drawn, on top of the grid.
+fn overlay_view<const N: usize>(variants: &[Variant], drawing: &[Cell], draw: bool) -> Html {
+    html! {
+        <svg class="sudoku-overlay" viewBox={format!("0 0 {N} {N}")}>
+            <defs>
+                <marker
+                    id="sudoku-arrowhead"
+                    viewBox="0 0 10 10"
+                    refX="9"
+                    refY="5"
+                    markerUnits="userSpaceOnUse"
+                    markerWidth="0.3"
+                    markerHeight="0.3"
+                    orient="auto"
+                >
+                    <path d="M 0 0 L 10 5 L 0 10" />
+                </marker>
+            </defs>
+            { variants.iter().map(variant_overlay).collect::<Html>() }
+            { if draw && drawing.len() > 1 {
+                html! {
+                    <polyline
+                        class="sudoku-line sudoku-line-preview"
+                        points={polyline_points(drawing.iter().copied().map(center))}
+                    />
+                }
+            } else {
+                html! {}
+            } }
+        </svg>
+    }
+}
+
+fn cage_at(variants: &[Variant], cell: Cell) -> Option<usize> {
+    variants.iter().position(|v| match v {
+        Variant::Killer { cells, .. } => cells.contains(&cell),
+        _ => false,
+    })
+}
+
+/// Cages that lose cells to the new cage also lose their total, which they could no longer reach.
+/// A click on a single cell of an existing cage only selects it.
+fn add_cage(mut variants: Vec<Variant>, mut cage: Vec<Cell>) -> Vec<Variant> {
+    if cage.is_empty() || (cage.len() == 1 && cage_at(&variants, cage[0]).is_some()) {
+        return variants;
+    }
+    for variant in variants.iter_mut() {
+        if let Variant::Killer { cells, total } = variant {
+            let len = cells.len();
+            cells.retain(|c| !cage.contains(c));
+            if cells.len() != len {
+                *total = None;
+            }
+        }
+    }
+    variants.retain(|v| !matches!(v, Variant::Killer { cells, .. } if cells.is_empty()));
+    cage.sort();
+    variants.push(Variant::Killer {
+        cells: cage,
+        total: None,
+    });
+    return variants;
+}
+
+/// Appends the digit to the total of the cage containing the cell. Without a digit, the last digit
+/// of the total is removed, and a cage without a total is deleted.
+fn cage_total_change(mut variants: Vec<Variant>, cell: Cell, digit: Option<u32>) -> Vec<Variant> {
+    if let Some(i) = cage_at(&variants, cell) {
+        if let Variant::Killer { total, .. } = &mut variants[i] {
+            match (digit, *total) {
+                (Some(d), Some(t)) if t < 100 => *total = Some(10 * t + d),
+                (Some(d), None) if d != 0 => *total = Some(d),
+                (None, Some(t)) => *total = Some(t / 10).filter(|&t| t != 0),
+                (None, None) => {
+                    variants.remove(i);
+                }
+                _ => {}
+            }
+        }
+    }
+    return variants;
+}
+
+fn cage_digit(event: &KeyboardEvent) -> Option<Option<u32>> {
+    let key = event.key_code();
+    if key >= ('0' as u32) && key <= ('9' as u32) {
+        return Some(Some(key - ('0' as u32)));
+    } else if (96..=105).contains(&key) {
+        return Some(Some(key - 96));
+    } else if key == 8 || key == 46 {
+        return Some(None);
+    }
+    return None;
+}
+
+/// The dashed cage outline and, in the first cell of the cage, its total.
+fn cage_view(variants: &[Variant], (row, col): Cell) -> Html {
+    for variant in variants {
+        if let Variant::Killer { cells, total } = variant {
+            if cells.contains(&(row, col)) {
+                let mut classes = Vec::with_capacity(4);
+                if row == 0 || !cells.contains(&(row - 1, col)) {
+                    classes.push("sudoku-cage-top");
+                }
+                if !cells.contains(&(row + 1, col)) {
+                    classes.push("sudoku-cage-bottom");
+                }
+                if col == 0 || !cells.contains(&(row, col - 1)) {
+                    classes.push("sudoku-cage-left");
+                }
+                if !cells.contains(&(row, col + 1)) {
+                    classes.push("sudoku-cage-right");
+                }
+                let label = match total {
+                    Some(t) if cells.first() == Some(&(row, col)) => {
+                        html! { <div class="sudoku-cage-total">{ t }</div> }
+                    }
+                    _ => html! {},
+                };
+                return html! {
+                    <>
+                        <div class={classes!("sudoku-cage", classes)} />
+                        { label }
+                    </>
+                };
+            }
+        }
+    }
+    return html! {};
+}
+
 fn focus_change(cells: &Vec<Vec<NodeRef>>, row: usize, col: usize, event: &KeyboardEvent) {
     let key = event.key_code();
     if key == 38 {
@@ -65,6 +566,12 @@ fn focus_change(cells: &Vec<Vec<NodeRef>>, row: usize, col: usize, event: &Keybo
 pub struct Props<const N: usize> {
     pub children: Children,
     pub sudoku: Sudoku<N>,
+    #[prop_or(default_domains())]
+    pub restrictions: SudokuDomains<N>,
+    #[prop_or(default_regions())]
+    pub regions: SudokuRegions<N>,
+    #[prop_or_default]
+    pub variants: Vec<Variant>,
     #[prop_or(empty_domains())]
     pub domains: SudokuDomains<N>,
     #[prop_or(empty_domains())]
@@ -72,6 +579,12 @@ pub struct Props<const N: usize> {
     #[prop_or(false)]
     pub working: bool,
     pub on_change: Callback<Sudoku<N>>,
+    #[prop_or_default]
+    pub on_restrictions_change: Callback<SudokuDomains<N>>,
+    #[prop_or_default]
+    pub on_regions_change: Callback<SudokuRegions<N>>,
+    #[prop_or_default]
+    pub on_variants_change: Callback<Vec<Variant>>,
 }
 
 #[function_component(SudokuInput)]
@@ -79,13 +592,24 @@ pub fn sudoku_input<const N: usize>(props: &Props<N>) -> Html {
     let Props {
         children,
         sudoku,
+        restrictions,
+        regions,
+        variants,
         domains,
         unsure,
         working,
         on_change,
+        on_restrictions_change,
+        on_regions_change,
+        on_variants_change,
     } = props;
     let selected = use_state_eq(|| None);
+    let mode = use_state_eq(|| EditMode::Digits);
+    let clue_kind = use_state_eq(|| ClueKind::Sandwich);
     let last = use_state_eq(|| None);
+    let drawing = use_state_eq(Vec::new);
+    let tool = use_state_eq(|| DrawTool::Line(LineKind::Thermometer));
+    let paste_error = use_state_eq(|| None);
     let mut cells = Vec::new();
     for _ in 0..N {
         let mut row = Vec::new();
@@ -96,30 +620,129 @@ pub fn sudoku_input<const N: usize>(props: &Props<N>) -> Html {
     }
     let onkeydown = {
         let selected = selected.clone();
-        let sudoku = sudoku.clone();
+        let mode = mode.clone();
+        let sudoku = *sudoku;
+        let restrictions = *restrictions;
+        let regions = *regions;
+        let variants = variants.clone();
         let on_change = on_change.clone();
+        let on_restrictions_change = on_restrictions_change.clone();
+        let on_regions_change = on_regions_change.clone();
+        let on_variants_change = on_variants_change.clone();
         let cells = cells.clone();
+        let paste_error = paste_error.clone();
         Callback::from(move |e| {
+            paste_error.set(None);
             if let Some((r, c)) = *selected {
                 focus_change(&cells, r, c, &e);
-                on_change.emit(sudoku_change(sudoku, r, c, &e));
+                if *mode == EditMode::Restrict {
+                    if let Some(toggle) = key_value::<N>(&e) {
+                        on_restrictions_change.emit(change_restriction(restrictions, r, c, toggle));
+                    }
+                } else if *mode == EditMode::Regions {
+                    if let Some(Some(v)) = key_value::<N>(&e) {
+                        let mut new = regions;
+                        new[r][c] = v as usize - 1;
+                        on_regions_change.emit(new);
+                    }
+                } else if *mode == EditMode::Cages {
+                    if let Some(digit) = cage_digit(&e) {
+                        on_variants_change.emit(cage_total_change(variants.clone(), (r, c), digit));
+                    }
+                } else {
+                    on_change.emit(sudoku_change(sudoku, r, c, &e));
+                }
             }
         })
     };
-    let onclick = |v| {
+    let onpaste = {
+        let on_change = on_change.clone();
+        let paste_error = paste_error.clone();
+        Callback::from(move |e: Event| {
+            // ClipboardEvent is still an unstable API in web-sys, but its data is not.
+            let text = js_sys::Reflect::get(&e, &"clipboardData".into())
+                .ok()
+                .and_then(|data| data.dyn_into::<DataTransfer>().ok())
+                .and_then(|data| data.get_data("text").ok());
+            if let Some(text) = text {
+                e.prevent_default();
+                // The whole grid is replaced at once, so the paste is a single change.
+                match parse_sudoku::<N>(&text) {
+                    Ok(new) => {
+                        paste_error.set(None);
+                        on_change.emit(new);
+                    }
+                    Err(err) => paste_error.set(Some(err.to_string())),
+                }
+            }
+        })
+    };
+    let onput = {
         let last = last.clone();
-        let sudoku = sudoku.clone();
+        let mode = mode.clone();
+        let sudoku = *sudoku;
+        let restrictions = *restrictions;
+        let regions = *regions;
+        let variants = variants.clone();
         let on_change = on_change.clone();
-        Callback::from(move |_| {
+        let on_restrictions_change = on_restrictions_change.clone();
+        let on_regions_change = on_regions_change.clone();
+        let on_variants_change = on_variants_change.clone();
+        Callback::from(move |put: Option<u32>| {
             if let Some((r, c)) = *last {
-                if v == 0 {
-                    on_change.emit(change_sudoku(sudoku, r, c, None));
+                if *mode == EditMode::Restrict {
+                    on_restrictions_change.emit(change_restriction(restrictions, r, c, put));
+                } else if *mode == EditMode::Regions {
+                    if let Some(v) = put {
+                        let mut new = regions;
+                        new[r][c] = v as usize - 1;
+                        on_regions_change.emit(new);
+                    }
+                } else if *mode == EditMode::Cages {
+                    on_variants_change.emit(cage_total_change(variants.clone(), (r, c), put));
                 } else {
-                    on_change.emit(change_sudoku(sudoku, r, c, Some(v)));
+                    on_change.emit(change_sudoku(sudoku, r, c, put));
                 }
             }
         })
     };
+    let onrestrict = |restriction: Restriction| {
+        let last = last.clone();
+        let restrictions = *restrictions;
+        let on_restrictions_change = on_restrictions_change.clone();
+        Callback::from(move |_| {
+            if let Some((r, c)) = *last {
+                let mut new = restrictions;
+                new[r][c] = restriction.digits::<N>();
+                on_restrictions_change.emit(new);
+            }
+        })
+    };
+    let extra = variants
+        .iter()
+        .flat_map(extra_regions::<N>)
+        .collect::<Vec<_>>();
+    let variant_button = |variant: Variant, label: &str| {
+        let variants = variants.clone();
+        let on_variants_change = on_variants_change.clone();
+        html! {
+            <button
+                class={classes!(variants.contains(&variant).then_some("input-mode-selected"))}
+                onclick={Callback::from(move |_| {
+                    on_variants_change.emit(toggle_variant(variants.clone(), variant.clone()))
+                })}
+            >{ label }</button>
+        }
+    };
+    let mode_button = |kind: EditMode, label: &str| {
+        let mode = mode.clone();
+        html! {
+            <button
+                class={classes!((*mode == kind).then_some("input-mode-selected"))}
+                onclick={Callback::from(move |_| mode.set(kind))}
+            >{ label }</button>
+        }
+    };
     let onfocus = |r, c| {
         let selected = selected.clone();
         let last = last.clone();
@@ -128,12 +751,92 @@ pub fn sudoku_input<const N: usize>(props: &Props<N>) -> Html {
             last.set(Some((r, c)));
         })
     };
+    let onmousedown = |r, c| {
+        let mode = mode.clone();
+        let drawing = drawing.clone();
+        Callback::from(move |_| {
+            if *mode == EditMode::Cages || *mode == EditMode::Regions || *mode == EditMode::Draw {
+                drawing.set(vec![(r, c)]);
+            }
+        })
+    };
+    let onmouseenter = |r, c| {
+        let drawing = drawing.clone();
+        Callback::from(move |_| {
+            if !drawing.is_empty() && !drawing.contains(&(r, c)) {
+                let mut cage = (*drawing).clone();
+                cage.push((r, c));
+                drawing.set(cage);
+            }
+        })
+    };
+    let onmouseup = {
+        let mode = mode.clone();
+        let tool = tool.clone();
+        let drawing = drawing.clone();
+        let regions = *regions;
+        let variants = variants.clone();
+        let on_regions_change = on_regions_change.clone();
+        let on_variants_change = on_variants_change.clone();
+        Callback::from(move |_| {
+            if !drawing.is_empty() {
+                if *mode == EditMode::Regions {
+                    on_regions_change.emit(paint_region(regions, &drawing));
+                } else if *mode == EditMode::Draw {
+                    on_variants_change.emit(add_drawing(
+                        variants.clone(),
+                        *tool,
+                        (*drawing).clone(),
+                    ));
+                } else {
+                    on_variants_change.emit(add_cage(variants.clone(), (*drawing).clone()));
+                }
+                drawing.set(Vec::new());
+            }
+        })
+    };
+    let onmouseleave = onmouseup.clone();
     let onblur = {
         let selected = selected.clone();
         Callback::from(move |_| {
             selected.set(None);
         })
     };
+    let clues = |side: Side| {
+        html! {
+            <div class={classes!("sudoku-clues", format!("sudoku-clues-{:?}", side).to_lowercase())}>
+                { (0..N).map(|i| {
+                    let (value, kind_class) = clue_text(variants, side, i);
+                    let variants = variants.clone();
+                    let on_variants_change = on_variants_change.clone();
+                    let kind = *clue_kind;
+                    let onchange = Callback::from(move |e: Event| {
+                        let value = e.target_unchecked_into::<HtmlInputElement>().value();
+                        let new = clue_change(variants.clone(), kind, side, i, &value);
+                        on_variants_change.emit(new);
+                    });
+                    html! {
+                        <input
+                            class={classes!("sudoku-clue", kind_class)}
+                            inputmode="numeric"
+                            {value}
+                            {onchange}
+                        />
+                    }
+                }).collect::<Html>() }
+            </div>
+        }
+    };
+    let clue_button = |kind: ClueKind, label: &str| {
+        let clue_kind = clue_kind.clone();
+        html! {
+            <button
+                class={classes!((*clue_kind == kind).then_some("input-mode-selected"))}
+                onclick={Callback::from(move |_| clue_kind.set(kind))}
+            >{ label }</button>
+        }
+    };
+    let invalid = invalid_regions(regions);
     let mut grid_classes = Vec::with_capacity(3);
     if *working {
         grid_classes.push("sudoku-working");
@@ -142,59 +845,129 @@ pub fn sudoku_input<const N: usize>(props: &Props<N>) -> Html {
         <div class="sudoku-input-wrapper">
             <div class={classes!("sudoku-grid-wrapper", grid_classes)}>
                 <div class="status-row">{ children.clone() }</div>
-                <div class={classes!("sudoku-grid", format!("sudoku-grid-{N}"))} {onkeydown} {onblur}>
-                    { (0..N).map(|r|
-                        (0..N).map(|c| {
-                            let mut cell_classes = Vec::with_capacity(5);
-                            cell_classes.push(format!("sudoku-cell-{}-x", r));
-                            cell_classes.push(format!("sudoku-cell-x-{}", c));
-                            if let Some((sr, sc)) = *selected {
-                                if (r, c) == (sr, sc) {
-                                    cell_classes.push("sudoku-cell-selected".to_owned());
+                <div class="sudoku-grid-frame">
+                    { clues(Side::Top) }
+                    { clues(Side::Left) }
+                    { clues(Side::Right) }
+                    { clues(Side::Bottom) }
+                    <div
+                        class={classes!("sudoku-grid", format!("sudoku-grid-{N}"))}
+                        {onkeydown}
+                        {onpaste}
+                        {onblur}
+                        {onmouseup}
+                        {onmouseleave}
+                    >
+                        { (0..N).map(|r|
+                            (0..N).map(|c| {
+                                let mut cell_classes = Vec::with_capacity(6);
+                                cell_classes.extend(region_borders(regions, (r, c)).into_iter().map(str::to_owned));
+                                if invalid.contains(&regions[r][c]) {
+                                    cell_classes.push("sudoku-cell-invalid-region".to_owned());
                                 }
-                                let sq = (N as f64).sqrt() as usize;
-                                if r == sr || c == sc || (r / sq, c / sq) == (sr / sq, sc / sq) {
-                                    cell_classes.push("sudoku-cell-constraint".to_owned());
+                                cell_classes.extend(restriction_class::<N>(restrictions[r][c]));
+                                cell_classes.extend(region_classes::<N>(variants, (r, c)).into_iter().map(str::to_owned));
+                                if *mode != EditMode::Draw && drawing.contains(&(r, c)) {
+                                    cell_classes.push("sudoku-cell-drawing".to_owned());
                                 }
-                            }
-                            html! {
-                                <div
-                                    id={format!("sudoku-cell-{}-{}", r, c)}
-                                    class={classes!("sudoku-cell", sudoku[r][c].and_then(|_| Some("sudoku-cell-set")), cell_classes)}
-                                    onfocus={onfocus(r, c)}
-                                >
-                                    <div class={classes!("sudoku-cell-result", format!("sudoku-results-{}", domains[r][c].len()))}>
-                                        { domains[r][c].clone().map(|e| html!{
-                                            <div class={classes!(
-                                                if unsure[r][c].contains(e) {
-                                                    "sudoku-result-unsure"
-                                                } else {
-                                                    "sudoku-result-sure"
-                                                }
-                                            )}>{format!("{:X}", (e + 1) & 0xf)}</div>
-                                        }).collect::<Html>() }
+                                if let Some((sr, sc)) = *selected {
+                                    if (r, c) == (sr, sc) {
+                                        cell_classes.push("sudoku-cell-selected".to_owned());
+                                    }
+                                    let in_region = extra.iter().any(|region| {
+                                        region.contains(&(r, c)) && region.contains(&(sr, sc))
+                                    });
+                                    if r == sr || c == sc || regions[r][c] == regions[sr][sc] || in_region {
+                                        cell_classes.push("sudoku-cell-constraint".to_owned());
+                                    }
+                                }
+                                html! {
+                                    <div
+                                        id={format!("sudoku-cell-{}-{}", r, c)}
+                                        class={classes!("sudoku-cell", sudoku[r][c].and_then(|_| Some("sudoku-cell-set")), cell_classes)}
+                                        onfocus={onfocus(r, c)}
+                                        onmousedown={onmousedown(r, c)}
+                                        onmouseenter={onmouseenter(r, c)}
+                                    >
+                                        { cage_view(variants, (r, c)) }
+                                        <div class={classes!("sudoku-cell-result", format!("sudoku-results-{}", domains[r][c].len()))}>
+                                            { domains[r][c].clone().map(|e| html!{
+                                                <div class={classes!(
+                                                    if unsure[r][c].contains(e) {
+                                                        "sudoku-result-unsure"
+                                                    } else {
+                                                        "sudoku-result-sure"
+                                                    }
+                                                )}>{format!("{:X}", (e + 1) & 0xf)}</div>
+                                            }).collect::<Html>() }
+                                        </div>
+                                        <div class="sudoku-cell-input" tabindex="0" type="number" ref={cells[r][c].clone()}>{
+                                            if let Some(v) = sudoku[r][c] {
+                                                format!("{:X}", v & 0xf)
+                                            } else {
+                                                "".to_owned()
+                                            }
+                                        }</div>
                                     </div>
-                                    <div class="sudoku-cell-input" tabindex="0" type="number" ref={cells[r][c].clone()}>{
-                                        if let Some(v) = sudoku[r][c] {
-                                            format!("{:X}", v & 0xf)
-                                        } else {
-                                            "".to_owned()
-                                        }
-                                    }</div>
-                                </div>
+                                }
+                            }).collect::<Html>()
+                        ).collect::<Html>() }
+                        { overlay_view::<N>(variants, &drawing, *mode == EditMode::Draw) }
+                    </div>
+                </div>
+                <div class="input-modes">
+                    { mode_button(EditMode::Digits, "Digits") }
+                    { mode_button(EditMode::Restrict, "Restrict") }
+                    { mode_button(EditMode::Regions, "Regions") }
+                    { mode_button(EditMode::Cages, "Cages") }
+                    { mode_button(EditMode::Draw, "Draw") }
+                    { if *mode == EditMode::Restrict {
+                        Restriction::ALL.into_iter().map(|restriction| html! {
+                            <button onclick={onrestrict(restriction)}>{ restriction.name() }</button>
+                        }).collect::<Html>()
+                    } else if *mode == EditMode::Draw {
+                        DrawTool::ALL.into_iter().map(|kind| {
+                            let tool = tool.clone();
+                            html! {
+                                <button
+                                    class={classes!((*tool == kind).then_some("input-mode-selected"))}
+                                    onclick={Callback::from(move |_| tool.set(kind))}
+                                >{ kind.name() }</button>
                             }
                         }).collect::<Html>()
-                    ).collect::<Html>() }
+                    } else {
+                        html! {}
+                    } }
+                </div>
+                { if invalid.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <div class="input-warning">{
+                            format!("every region needs {N} connected cells")
+                        }</div>
+                    }
+                } }
+                { match &*paste_error {
+                    Some(err) => html! {
+                        <div class="input-warning">{ format!("could not paste the puzzle: {}", err) }</div>
+                    },
+                    None => html! {},
+                } }
+                <div class="input-modes">
+                    { variant_button(Variant::Windoku, "Windoku") }
+                    { variant_button(Variant::DisjointGroups, "Disjoint groups") }
+                    { variant_button(Variant::CenterDot, "Center dot") }
+                    { variant_button(Variant::NonConsecutive, "Non-consecutive") }
+                </div>
+                <div class="input-modes">
+                    { clue_button(ClueKind::Sandwich, "Sandwich") }
+                    { clue_button(ClueKind::LittleKiller(true), "Little killer ↘") }
+                    { clue_button(ClueKind::LittleKiller(false), "Little killer ↙") }
+                    { clue_button(ClueKind::Skyscraper, "Skyscraper") }
                 </div>
             </div>
-            <div class={classes!("sudoku-input", format!("sudoku-input-{N}"))}>
-                { (0..=N as u32).map(|n| html! {
-                    <button
-                        class={classes!("number-button", format!("number-button-{}", n))}
-                        onclick={onclick(n)}
-                    >{ if n == 0 { "_".to_owned() } else { format!("{:X}", n & 0xf) } }</button>
-                }).collect::<Html>() }
-            </div>
+            <NumberButtons<N> on_put={onput} />
         </div>
     }
 }
@@ -1,17 +1,35 @@
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
+use yew_router::prelude::*;
 
+use crate::components::app::Route;
+use crate::components::puzzle_library::PuzzleLibrary;
+use crate::components::solver_controls::SolverControls;
 use crate::components::sudoku_input::SudokuInput;
 use crate::solver::domain::DomainSet;
+use crate::solver::fpuzzles::left_out;
+use crate::solver::links::{
+    decode_puzzle, encode_puzzle, fpuzzles_link, import_link, sudokupad_link,
+};
 use crate::solver::sudoku::{
-    create_problem, default_domains, empty_domains, empty_sudoku, reshape_domains, sudoku_domains,
-    Sudoku, SudokuDomains,
+    create_problem, default_domains, default_regions, empty_domains, empty_sudoku, reshape_domains,
+    sudoku_domains, CandidatesText, Puzzle, Sudoku, SudokuDomains, SudokuFormat, SudokuRegions,
+    SudokuText, Variant,
 };
 use crate::workers::{MinimizingWorker, SolvingWorker};
 
 pub enum SolverMessage<const N: usize> {
     Change(Sudoku<N>),
+    ChangeRestrictions(SudokuDomains<N>),
+    ChangeRegions(SudokuRegions<N>),
+    ChangeVariants(Vec<Variant>),
+    Import(String),
+    Load(Puzzle<N>),
+    ChangeExport(usize),
+    Copy,
     Solve,
     Clear,
     Solved(Option<Sudoku<N>>, usize),
@@ -20,35 +38,186 @@ pub enum SolverMessage<const N: usize> {
     Redo,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Export {
+    Givens(SudokuFormat),
+    Solution(SudokuFormat),
+    Candidates,
+}
+
+const EXPORTS: [Export; 7] = [
+    Export::Givens(SudokuFormat::Line),
+    Export::Givens(SudokuFormat::Grid),
+    Export::Givens(SudokuFormat::Sdk),
+    Export::Solution(SudokuFormat::Line),
+    Export::Solution(SudokuFormat::Grid),
+    Export::Solution(SudokuFormat::Sdk),
+    Export::Candidates,
+];
+
+impl Export {
+    fn name(&self) -> &'static str {
+        match self {
+            Export::Givens(SudokuFormat::Line) => "Givens (one line)",
+            Export::Givens(SudokuFormat::Grid) => "Givens (grid)",
+            Export::Givens(SudokuFormat::Sdk) => "Givens (.sdk)",
+            Export::Solution(SudokuFormat::Line) => "Solution (one line)",
+            Export::Solution(SudokuFormat::Grid) => "Solution (grid)",
+            Export::Solution(SudokuFormat::Sdk) => "Solution (.sdk)",
+            Export::Candidates => "Candidates",
+        }
+    }
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            Export::Givens(SudokuFormat::Sdk) | Export::Solution(SudokuFormat::Sdk) => "sudoku.sdk",
+            _ => "sudoku.txt",
+        }
+    }
+}
+
+/// The query of the links that reproduce the current puzzle.
+#[derive(Serialize, Deserialize)]
+struct ShareQuery {
+    puzzle: String,
+}
+
+/// How many undo and redo steps are kept in the local storage.
+const SAVED_HISTORY: usize = 100;
+
+/// Only the puzzles of the history are saved, the domains are computed again once an item is visited.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    puzzles: Vec<String>,
+    hist_pos: usize,
+}
+
+pub(crate) fn local_storage() -> Option<web_sys::Storage> {
+    return web_sys::window()?.local_storage().ok()?;
+}
+
+/// The clipboard is not part of the stable web-sys API, so it is called through reflection.
+fn copy_to_clipboard(text: &str) {
+    let clipboard = web_sys::window()
+        .and_then(|window| js_sys::Reflect::get(&window.navigator(), &"clipboard".into()).ok());
+    if let Some(clipboard) = clipboard {
+        let write = js_sys::Reflect::get(&clipboard, &"writeText".into())
+            .ok()
+            .and_then(|write| write.dyn_into::<js_sys::Function>().ok());
+        if let Some(write) = write {
+            let _ = write.call1(&clipboard, &text.into());
+        }
+    }
+}
+
+fn data_url(text: &str) -> String {
+    let mut res = String::from("data:text/plain;charset=utf-8,");
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            res.push(byte as char);
+        } else {
+            res.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    return res;
+}
+
 #[derive(Clone)]
 pub struct SudokuHistoryItem<const N: usize> {
     sudoku: Sudoku<N>,
+    restrictions: SudokuDomains<N>,
+    regions: SudokuRegions<N>,
+    variants: Vec<Variant>,
     domains: SudokuDomains<N>,
     unsure: SudokuDomains<N>,
     change: usize,
     solved: Option<bool>,
+    /// Whether the domains have been reduced, which is put off for the items of a loaded history
+    /// until they are visited.
+    reduced: bool,
 }
 
 impl<const N: usize> SudokuHistoryItem<N> {
     fn default() -> Self {
         SudokuHistoryItem {
             sudoku: empty_sudoku(),
+            restrictions: default_domains(),
+            regions: default_regions(),
+            variants: Vec::new(),
             domains: default_domains(),
             unsure: empty_domains(),
             change: 0,
             solved: None,
+            reduced: true,
         }
     }
 
-    fn new(sudoku: Sudoku<N>, domains: SudokuDomains<N>) -> Self {
-        let problem = create_problem(&domains);
-        let domains = reshape_domains(problem.reduced_domains());
+    fn new(
+        sudoku: Sudoku<N>,
+        restrictions: SudokuDomains<N>,
+        regions: SudokuRegions<N>,
+        variants: Vec<Variant>,
+        domains: SudokuDomains<N>,
+    ) -> Self {
+        let mut res = Self::unreduced(sudoku, restrictions, regions, variants, domains);
+        res.reduce();
+        return res;
+    }
+
+    fn unreduced(
+        sudoku: Sudoku<N>,
+        restrictions: SudokuDomains<N>,
+        regions: SudokuRegions<N>,
+        variants: Vec<Variant>,
+        domains: SudokuDomains<N>,
+    ) -> Self {
         SudokuHistoryItem {
             sudoku: sudoku,
+            restrictions: restrictions,
+            regions: regions,
+            variants: variants,
             domains: domains,
             unsure: domains,
             change: 0,
             solved: None,
+            reduced: false,
+        }
+    }
+
+    fn reduce(&mut self) {
+        if !self.reduced {
+            let problem = create_problem(&self.domains, &self.regions, &self.variants);
+            self.domains = reshape_domains(problem.reduced_domains());
+            self.unsure = self.domains;
+            self.reduced = true;
+        }
+    }
+
+    fn has_layout(
+        &self,
+        restrictions: &SudokuDomains<N>,
+        regions: &SudokuRegions<N>,
+        variants: &[Variant],
+    ) -> bool {
+        self.restrictions == *restrictions && self.regions == *regions && self.variants == variants
+    }
+
+    fn has_puzzle(
+        &self,
+        sudoku: &Sudoku<N>,
+        restrictions: &SudokuDomains<N>,
+        regions: &SudokuRegions<N>,
+        variants: &[Variant],
+    ) -> bool {
+        self.sudoku == *sudoku && self.has_layout(restrictions, regions, variants)
+    }
+
+    fn puzzle(&self) -> Puzzle<N> {
+        Puzzle {
+            sudoku: self.sudoku,
+            restrictions: self.restrictions,
+            regions: self.regions,
+            variants: self.variants.clone(),
         }
     }
 }
@@ -57,12 +226,17 @@ pub struct SudokuSolver<const N: usize>
 where
     Sudoku<N>: Serialize + DeserializeOwned,
     SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
     history: Vec<SudokuHistoryItem<N>>,
     hist_pos: usize,
     change: usize,
     solving: Option<usize>,
     minimizing: Option<usize>,
+    import_error: Option<String>,
+    export: Export,
+    shared: Option<String>,
+    saved: (usize, usize),
     minimize_bridge: Box<dyn Bridge<MinimizingWorker<N>>>,
     solver_bridge: Box<dyn Bridge<SolvingWorker<N>>>,
 }
@@ -88,6 +262,8 @@ fn is_sudoku_subset<const N: usize>(new: &Sudoku<N>, hist: &Sudoku<N>) -> bool {
     return true;
 }
 
+/// Narrows the domains of an earlier puzzle to the new givens. A given outside the domain of its
+/// cell, for example one that breaks a restriction, leaves the cell empty.
 fn adjust_domains<const N: usize>(
     mut domains: SudokuDomains<N>,
     sudoku: &Sudoku<N>,
@@ -95,7 +271,7 @@ fn adjust_domains<const N: usize>(
     for i in 0..N {
         for j in 0..N {
             if let Some(v) = sudoku[i][j] {
-                domains[i][j] = DomainSet::singleton(v - 1);
+                domains[i][j].retain_all(DomainSet::singleton(v - 1));
             }
         }
     }
@@ -106,6 +282,7 @@ impl<const N: usize> SudokuSolver<N>
 where
     Sudoku<N>: Serialize + DeserializeOwned,
     SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
     fn has_no_solution(&self) -> bool {
         if self.current_history().solved == Some(false) {
@@ -134,6 +311,132 @@ where
         return false;
     }
 
+    /// The solution, once every cell has a single value that is known to be part of a solution.
+    fn solution(&self) -> Option<Sudoku<N>> {
+        let SudokuHistoryItem {
+            domains, unsure, ..
+        } = self.current_history();
+        let mut res = empty_sudoku();
+        for i in 0..N {
+            for j in 0..N {
+                if !domains[i][j].is_singleton() || !unsure[i][j].is_empty() {
+                    return None;
+                }
+                res[i][j] = domains[i][j].get_any().map(|v| v + 1);
+            }
+        }
+        return Some(res);
+    }
+
+    fn export_text(&self) -> Option<String> {
+        match self.export {
+            Export::Givens(format) => Some(
+                SudokuText {
+                    sudoku: &self.current_history().sudoku,
+                    format,
+                }
+                .to_string(),
+            ),
+            Export::Solution(format) => self.solution().map(|sudoku| {
+                SudokuText {
+                    sudoku: &sudoku,
+                    format,
+                }
+                .to_string()
+            }),
+            Export::Candidates => Some(
+                CandidatesText {
+                    domains: &self.current_history().domains,
+                }
+                .to_string(),
+            ),
+        }
+    }
+
+    /// Every route keeps its own state, so that the grid sizes do not mix.
+    fn storage_key(ctx: &Context<Self>) -> Option<String> {
+        let route = ctx.link().route::<Route>()?;
+        return Some(format!("sudoku-solver-state:{}", route.to_path()));
+    }
+
+    fn load_state(&mut self, ctx: &Context<Self>) {
+        let saved = Self::storage_key(ctx)
+            .and_then(|key| local_storage()?.get_item(&key).ok()?)
+            .and_then(|json| serde_json::from_str::<SavedState>(&json).ok());
+        if let Some(saved) = saved {
+            let puzzles = saved
+                .puzzles
+                .iter()
+                .map(|data| decode_puzzle::<N>(data))
+                .collect::<Result<Vec<_>, _>>();
+            if let Ok(puzzles) = puzzles {
+                if saved.hist_pos < puzzles.len() {
+                    self.history = puzzles
+                        .into_iter()
+                        .map(|puzzle| {
+                            let domains = sudoku_domains(&puzzle.sudoku, &puzzle.restrictions);
+                            self.change += 1;
+                            let mut hist = SudokuHistoryItem::unreduced(
+                                puzzle.sudoku,
+                                puzzle.restrictions,
+                                puzzle.regions,
+                                puzzle.variants,
+                                domains,
+                            );
+                            hist.change = self.change;
+                            return hist;
+                        })
+                        .collect();
+                    self.hist_pos = saved.hist_pos;
+                }
+            }
+        }
+        self.saved = (self.change, self.hist_pos);
+    }
+
+    /// Writes the state only after the history or the position in it has changed, which is
+    /// always accompanied by a new change number. An empty grid without any history removes the
+    /// saved state.
+    fn save_state(&mut self, ctx: &Context<Self>) {
+        if self.saved == (self.change, self.hist_pos) {
+            return;
+        }
+        self.saved = (self.change, self.hist_pos);
+        if let (Some(storage), Some(key)) = (local_storage(), Self::storage_key(ctx)) {
+            let res =
+                if self.history.len() == 1 && self.current_history().puzzle() == Puzzle::empty() {
+                    storage.remove_item(&key)
+                } else {
+                    let start = self.hist_pos.saturating_sub(SAVED_HISTORY);
+                    let end = self.history.len().min(self.hist_pos + SAVED_HISTORY + 1);
+                    let saved = SavedState {
+                        puzzles: self.history[start..end]
+                            .iter()
+                            .map(|hist| encode_puzzle(&hist.puzzle()))
+                            .collect(),
+                        hist_pos: self.hist_pos - start,
+                    };
+                    match serde_json::to_string(&saved) {
+                        Ok(json) => storage.set_item(&key, &json),
+                        Err(err) => Err(err.to_string().into()),
+                    }
+                };
+            if let Err(err) = res {
+                web_sys::console::warn_2(&"Failed to save the sudoku state:".into(), &err);
+            }
+        }
+    }
+
+    fn load_puzzle(&mut self, puzzle: Puzzle<N>) {
+        self.history_push_sudoku(
+            puzzle.sudoku,
+            puzzle.restrictions,
+            puzzle.regions,
+            puzzle.variants,
+        );
+        self.start_domain_compute();
+    }
+
     fn current_history(&self) -> &SudokuHistoryItem<N> {
         &self.history[self.hist_pos]
     }
@@ -143,6 +446,7 @@ where
     }
 
     fn start_domain_compute(&mut self) {
+        self.current_history_mut().reduce();
         if self.minimizing == None
             && self
                 .current_history()
@@ -155,6 +459,8 @@ where
             self.minimize_bridge.send((
                 self.current_history().domains,
                 self.current_history().unsure,
+                self.current_history().regions,
+                self.current_history().variants.clone(),
                 self.current_history().change,
             ));
         }
@@ -168,14 +474,24 @@ where
         }
         self.history.push(hist);
         self.hist_pos += 1;
+        self.current_history_mut().reduce();
     }
 
-    fn smallest_subset(&self, sudoku: &Sudoku<N>) -> Option<usize> {
-        let mut min_count = count_domain_values(&sudoku_domains(sudoku));
+    fn smallest_subset(
+        &self,
+        sudoku: &Sudoku<N>,
+        restrictions: &SudokuDomains<N>,
+        regions: &SudokuRegions<N>,
+        variants: &[Variant],
+    ) -> Option<usize> {
+        let mut min_count = count_domain_values(&sudoku_domains(sudoku, restrictions));
         let mut best = None;
         for i in (0..self.history.len()).rev() {
             let cnt = count_domain_values(&self.history[i].domains);
-            if cnt < min_count && is_sudoku_subset(sudoku, &self.history[i].sudoku) {
+            if cnt < min_count
+                && self.history[i].has_layout(restrictions, regions, variants)
+                && is_sudoku_subset(sudoku, &self.history[i].sudoku)
+            {
                 min_count = cnt;
                 best = Some(i);
             }
@@ -183,22 +499,37 @@ where
         return best;
     }
 
-    fn history_push_sudoku(&mut self, sudoku: Sudoku<N>) {
-        if sudoku != self.current_history().sudoku {
+    fn history_push_sudoku(
+        &mut self,
+        sudoku: Sudoku<N>,
+        restrictions: SudokuDomains<N>,
+        regions: SudokuRegions<N>,
+        variants: Vec<Variant>,
+    ) {
+        if !self
+            .current_history()
+            .has_puzzle(&sudoku, &restrictions, &regions, &variants)
+        {
             for i in (0..self.history.len()).rev() {
-                if self.history[i].sudoku == sudoku {
+                if self.history[i].has_puzzle(&sudoku, &restrictions, &regions, &variants) {
                     self.history_push(self.history[i].clone());
                     return;
                 }
             }
-            if let Some(idx) = self.smallest_subset(&sudoku) {
-                self.history_push(SudokuHistoryItem::new(
-                    sudoku,
-                    adjust_domains(self.history[idx].domains, &sudoku),
-                ));
+            let domains = if let Some(idx) =
+                self.smallest_subset(&sudoku, &restrictions, &regions, &variants)
+            {
+                adjust_domains(self.history[idx].domains, &sudoku)
             } else {
-                self.history_push(SudokuHistoryItem::new(sudoku, sudoku_domains(&sudoku)));
-            }
+                sudoku_domains(&sudoku, &restrictions)
+            };
+            self.history_push(SudokuHistoryItem::new(
+                sudoku,
+                restrictions,
+                regions,
+                variants,
+                domains,
+            ));
         }
     }
 }
@@ -207,17 +538,22 @@ impl<const N: usize> Component for SudokuSolver<N>
 where
     Sudoku<N>: Serialize + DeserializeOwned,
     SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
     type Message = SolverMessage<N>;
     type Properties = ();
 
     fn create(ctx: &Context<Self>) -> Self {
-        Self {
+        let mut res = Self {
             history: vec![SudokuHistoryItem::default()],
             hist_pos: 0,
             change: 0,
             solving: None,
             minimizing: None,
+            import_error: None,
+            export: Export::Givens(SudokuFormat::Line),
+            shared: None,
+            saved: (0, 0),
             solver_bridge: SolvingWorker::bridge(
                 ctx.link()
                     .callback(|(sol, id)| Self::Message::Solved(sol, id)),
@@ -226,24 +562,112 @@ where
                 ctx.link()
                     .callback(|(sol, uns, id)| Self::Message::Minimized(sol, uns, id)),
             ),
+        };
+        res.load_state(ctx);
+        let query = ctx
+            .link()
+            .location()
+            .and_then(|location| location.query::<ShareQuery>().ok());
+        if let Some(query) = query {
+            if let Ok(puzzle) = decode_puzzle::<N>(&query.puzzle) {
+                res.shared = Some(query.puzzle);
+                if puzzle != res.current_history().puzzle() {
+                    res.history_push_sudoku(
+                        puzzle.sudoku,
+                        puzzle.restrictions,
+                        puzzle.regions,
+                        puzzle.variants,
+                    );
+                }
+            }
         }
+        res.start_domain_compute();
+        return res;
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Self::Message::Change(new) => {
                 if new != self.current_history().sudoku && self.solving == None {
-                    self.history_push_sudoku(new);
+                    let restrictions = self.current_history().restrictions;
+                    let regions = self.current_history().regions;
+                    let variants = self.current_history().variants.clone();
+                    self.history_push_sudoku(new, restrictions, regions, variants);
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::ChangeRestrictions(new) => {
+                if new != self.current_history().restrictions && self.solving == None {
+                    let sudoku = self.current_history().sudoku;
+                    let regions = self.current_history().regions;
+                    let variants = self.current_history().variants.clone();
+                    self.history_push_sudoku(sudoku, new, regions, variants);
                     self.start_domain_compute();
                 }
             }
+            Self::Message::ChangeRegions(new) => {
+                if new != self.current_history().regions && self.solving == None {
+                    let sudoku = self.current_history().sudoku;
+                    let restrictions = self.current_history().restrictions;
+                    let variants = self.current_history().variants.clone();
+                    self.history_push_sudoku(sudoku, restrictions, new, variants);
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::ChangeVariants(new) => {
+                if new != self.current_history().variants && self.solving == None {
+                    let sudoku = self.current_history().sudoku;
+                    let restrictions = self.current_history().restrictions;
+                    let regions = self.current_history().regions;
+                    self.history_push_sudoku(sudoku, restrictions, regions, new);
+                    self.start_domain_compute();
+                }
+            }
+            Self::Message::Import(link) => {
+                if self.solving == None {
+                    match import_link::<N>(&link) {
+                        Ok(puzzle) => {
+                            self.import_error = None;
+                            self.load_puzzle(puzzle);
+                        }
+                        Err(err) => self.import_error = Some(err.to_string()),
+                    }
+                }
+            }
+            Self::Message::Load(puzzle) => {
+                if self.solving == None {
+                    self.load_puzzle(puzzle);
+                }
+            }
+            Self::Message::ChangeExport(index) => {
+                if let Some(&export) = EXPORTS.get(index) {
+                    self.export = export;
+                }
+            }
+            Self::Message::Copy => {
+                if let Some(text) = self.export_text() {
+                    copy_to_clipboard(&text);
+                }
+            }
             Self::Message::Solve => {
                 if self.solving == None {
                     self.solving = Some(self.change);
-                    self.solver_bridge
-                        .send((self.current_history().sudoku, self.change));
+                    let SudokuHistoryItem {
+                        sudoku,
+                        restrictions,
+                        regions,
+                        variants,
+                        ..
+                    } = self.current_history();
+                    self.solver_bridge.send((
+                        sudoku_domains(sudoku, restrictions),
+                        *regions,
+                        variants.clone(),
+                        self.change,
+                    ));
                 }
             }
+            // Clearing also forgets the saved state, see `save_state`.
             Self::Message::Clear => {
                 if self.solving == None {
                     self.change += 1;
@@ -257,7 +681,10 @@ where
                     if let Some(sol) = res {
                         if sol != self.current_history().sudoku {
                             self.change += 1;
-                            self.history_push_sudoku(sol);
+                            let restrictions = self.current_history().restrictions;
+                            let regions = self.current_history().regions;
+                            let variants = self.current_history().variants.clone();
+                            self.history_push_sudoku(sol, restrictions, regions, variants);
                         }
                         self.current_history_mut().solved = Some(true);
                     } else {
@@ -272,6 +699,7 @@ where
                         if self.history[i].change == id {
                             self.history[i].domains = sol;
                             self.history[i].unsure = uns;
+                            self.history[i].reduced = true;
                             break;
                         }
                     }
@@ -291,50 +719,110 @@ where
                 }
             }
         }
+        self.save_state(ctx);
         return true;
     }
 
+    /// Keeps the link in the address bar in sync with the current puzzle.
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        let puzzle = self.current_history().puzzle();
+        let data = (puzzle != Puzzle::empty()).then(|| encode_puzzle(&puzzle));
+        if data != self.shared {
+            if let (Some(history), Some(route)) =
+                (ctx.link().history(), ctx.link().route::<Route>())
+            {
+                match &data {
+                    Some(data) => {
+                        let query = ShareQuery {
+                            puzzle: data.clone(),
+                        };
+                        let _ = history.replace_with_query(route, query);
+                    }
+                    None => history.replace(route),
+                }
+            }
+            self.shared = data;
+        }
+    }
+
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let puzzle = self.current_history().puzzle();
+        let export_text = self.export_text();
+        let download = export_text.as_deref().map(data_url);
+        let left_out_text = match left_out(&puzzle).as_slice() {
+            [] => String::new(),
+            parts => format!("The links leave out the {}.", parts.join(" and the ")),
+        };
         html! {
             <div class="sudoku-solver">
                 <SudokuInput<N>
                     sudoku={self.current_history().sudoku}
+                    restrictions={self.current_history().restrictions}
+                    regions={self.current_history().regions}
+                    variants={self.current_history().variants.clone()}
                     domains={self.current_history().domains}
                     unsure={self.current_history().unsure}
                     working={self.solving != None}
                     on_change={ctx.link().callback(|new| Self::Message::Change(new))}
+                    on_restrictions_change={ctx.link().callback(|new| Self::Message::ChangeRestrictions(new))}
+                    on_regions_change={ctx.link().callback(|new| Self::Message::ChangeRegions(new))}
+                    on_variants_change={ctx.link().callback(|new| Self::Message::ChangeVariants(new))}
                 >
-                    <div class="info-text">{
-                        if self.has_no_solution() {
-                            "no solutions"
-                        } else if self.has_multiple_solution() {
-                            "multiple solutions"
-                        } else {
-                            ""
+                    <SolverControls
+                        status={
+                            if self.has_no_solution() {
+                                "no solutions"
+                            } else if self.has_multiple_solution() {
+                                "multiple solutions"
+                            } else {
+                                ""
+                            }
                         }
-                    }</div>
-                    <div class="buttons">
-                        <button
-                            onclick={ctx.link().callback(|_| Self::Message::Undo)}
-                            disabled={self.hist_pos == 0}
-                        >
-                            <svg xmlns="http://www.w3.org/2000/svg" height="24px" viewBox="0 0 24 24" width="24px"><path d="M0 0h24v24H0z" fill="none"/><path d="M12.5 8c-2.65 0-5.05.99-6.9 2.6L2 7v9h9l-3.62-3.62c1.39-1.16 3.16-1.88 5.12-1.88 3.54 0 6.55 2.31 7.6 5.5l2.37-.78C21.08 11.03 17.15 8 12.5 8z"/></svg>
-                        </button>
-                        <button
-                            onclick={ctx.link().callback(|_| Self::Message::Redo)}
-                            disabled={self.hist_pos == self.history.len() - 1}
-                        >
-                            <svg xmlns="http://www.w3.org/2000/svg" height="24px" viewBox="0 0 24 24" width="24px"><path d="M0 0h24v24H0z" fill="none"/><path d="M18.4 10.6C16.55 8.99 14.15 8 11.5 8c-4.65 0-8.58 3.03-9.96 7.22L3.9 16c1.05-3.19 4.05-5.5 7.6-5.5 1.95 0 3.73.72 5.12 1.88L13 16h9V7l-3.6 3.6z"/></svg>
-                        </button>
-                        <button
-                            onclick={ctx.link().callback(|_| Self::Message::Solve)}
-                            disabled={self.solving != None || self.current_history().solved != None}
-                        >{"Solve"}</button>
-                        <button
-                            onclick={ctx.link().callback(|_| Self::Message::Clear)}
-                        >{"Clear"}</button>
-                    </div>
+                        can_undo={self.hist_pos != 0}
+                        can_redo={self.hist_pos != self.history.len() - 1}
+                        can_solve={self.solving == None && self.current_history().solved == None}
+                        on_undo={ctx.link().callback(|_| Self::Message::Undo)}
+                        on_redo={ctx.link().callback(|_| Self::Message::Redo)}
+                        on_solve={ctx.link().callback(|_| Self::Message::Solve)}
+                        on_clear={ctx.link().callback(|_| Self::Message::Clear)}
+                    />
                 </SudokuInput<N>>
+                <div class="import-row">
+                    <input
+                        placeholder="Import f-puzzles or SudokuPad link"
+                        onchange={ctx.link().callback(|e: Event| {
+                            Self::Message::Import(e.target_unchecked_into::<HtmlInputElement>().value())
+                        })}
+                    />
+                    <div class="info-text">{ self.import_error.clone().unwrap_or_default() }</div>
+                    <div class="export-links">
+                        <a href={fpuzzles_link(&puzzle)} target="_blank">{"Open in f-puzzles"}</a>
+                        <a href={sudokupad_link(&puzzle)} target="_blank">{"Open in SudokuPad"}</a>
+                    </div>
+                    <div class="info-text">{ left_out_text }</div>
+                </div>
+                <div class="export-row">
+                    <select
+                        onchange={ctx.link().callback(|e: Event| {
+                            let index = e.target_unchecked_into::<HtmlSelectElement>().selected_index();
+                            Self::Message::ChangeExport(index as usize)
+                        })}
+                    >
+                        { for EXPORTS.iter().map(|export| html! {
+                            <option selected={*export == self.export}>{ export.name() }</option>
+                        }) }
+                    </select>
+                    <button
+                        onclick={ctx.link().callback(|_| Self::Message::Copy)}
+                        disabled={export_text.is_none()}
+                    >{"Copy"}</button>
+                    <a href={download} download={self.export.file_name()}>{"Download"}</a>
+                </div>
+                <PuzzleLibrary<N>
+                    puzzle={puzzle}
+                    solved={self.solution() == Some(self.current_history().sudoku)}
+                    on_load={ctx.link().callback(|puzzle| Self::Message::Load(puzzle))}
+                />
             </div>
         }
     }
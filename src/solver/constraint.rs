@@ -0,0 +1,513 @@
+use std::{collections::HashSet, hash::Hash};
+
+use crate::solver::domain::DomainSet;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    AllDifferent(Vec<usize>),
+    /// The sum of `weight * value` over all terms must be equal to the total.
+    Sum(Vec<(usize, i64)>, i64),
+    /// The absolute difference of the two variables must not be contained in the set.
+    ForbiddenDifference(usize, usize, DomainSet),
+    /// The values are within a range no larger than the number of variables.
+    Consecutive(Vec<usize>),
+    /// The values are strictly increasing.
+    Increasing(Vec<usize>),
+    /// The value of the second variable is contained in the set at the index of the first value.
+    Table(usize, usize, Vec<DomainSet>),
+    /// The two crust values appear exactly once, and the digits between them (counting each
+    /// value as value plus one) sum to the total.
+    Sandwich(Vec<usize>, (u32, u32), i64),
+    /// The values are all different, and the number of values that are larger than all values
+    /// before them is equal to the count.
+    Skyscraper(Vec<usize>, usize),
+    /// Every value `j` appears between `bounds[j].0` and `bounds[j].1` times. Values without
+    /// bounds can not appear at all.
+    GlobalCardinality(Vec<usize>, Vec<(usize, usize)>),
+}
+
+impl Constraint {
+    /// The variables in the scope of the constraint, without collecting them, as this is called
+    /// for every propagation.
+    pub fn variables(&self) -> impl Iterator<Item = usize> + '_ {
+        let (vars, terms, pair): (&[usize], &[(usize, i64)], _) = match self {
+            Constraint::AllDifferent(vars)
+            | Constraint::Consecutive(vars)
+            | Constraint::Increasing(vars)
+            | Constraint::Sandwich(vars, _, _)
+            | Constraint::Skyscraper(vars, _)
+            | Constraint::GlobalCardinality(vars, _) => (vars, &[], None),
+            Constraint::Sum(terms, _) => (&[], terms, None),
+            Constraint::ForbiddenDifference(a, b, _) | Constraint::Table(a, b, _) => {
+                (&[], &[], Some([*a, *b]))
+            }
+        };
+        return vars
+            .iter()
+            .copied()
+            .chain(terms.iter().map(|&(v, _)| v))
+            .chain(pair.into_iter().flatten());
+    }
+
+    pub fn reduce(&self, domains: &mut [DomainSet]) -> bool {
+        match self {
+            Constraint::AllDifferent(vars) => reduce_all_different(domains, vars),
+            Constraint::Sum(terms, total) => reduce_sum(domains, terms, *total),
+            Constraint::ForbiddenDifference(a, b, forbidden) => {
+                reduce_difference(domains, *a, *b, *forbidden)
+                    && reduce_difference(domains, *b, *a, *forbidden)
+            }
+            Constraint::Consecutive(vars) => reduce_consecutive(domains, vars),
+            Constraint::Increasing(vars) => reduce_increasing(domains, vars),
+            Constraint::Table(a, b, allowed) => reduce_table(domains, *a, *b, allowed),
+            Constraint::Sandwich(vars, crusts, total) => {
+                reduce_sandwich(domains, vars, *crusts, *total)
+            }
+            Constraint::Skyscraper(vars, count) => reduce_skyscraper(domains, vars, *count),
+            Constraint::GlobalCardinality(vars, bounds) => {
+                reduce_global_cardinality(domains, vars, bounds)
+            }
+        }
+    }
+}
+
+fn is_all_different_satisfiable(
+    domains: &[DomainSet],
+    constr: &[usize],
+    mut remove: DomainSet,
+    mut taken: DomainSet,
+) -> bool {
+    let mut left = constr.len() - taken.len();
+    let mut change = true;
+    while change && left > 0 {
+        change = false;
+        for (i, &w) in constr.iter().enumerate() {
+            if !taken.contains(i as u32) {
+                let rem = domains[w].without_all(remove);
+                if rem.is_singleton() {
+                    remove.add_all(rem);
+                    taken.add(i as u32);
+                    left -= 1;
+                    change = true;
+                } else if rem.is_empty() {
+                    return false;
+                }
+            }
+        }
+    }
+    let mut without = DomainSet::empty();
+    left = 0;
+    for (i, &w) in constr.iter().enumerate() {
+        if !taken.contains(i as u32) {
+            left += 1;
+            without.add_all(domains[w]);
+            if without.without_all(remove).len() < left {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+fn reduce_all_different(domains: &mut [DomainSet], constr: &[usize]) -> bool {
+    for (i, &v) in constr.iter().enumerate() {
+        for j in domains[v] {
+            if !is_all_different_satisfiable(
+                domains,
+                constr,
+                DomainSet::singleton(j),
+                DomainSet::singleton(i as u32),
+            ) {
+                domains[v].remove(j);
+                if domains[v].is_empty() {
+                    return false;
+                }
+            }
+        }
+    }
+    return true;
+}
+
+fn term_bounds(domain: DomainSet, weight: i64) -> (i64, i64) {
+    let mut min = i64::MAX;
+    let mut max = i64::MIN;
+    for j in domain {
+        min = min.min(weight * j as i64);
+        max = max.max(weight * j as i64);
+    }
+    return (min, max);
+}
+
+fn reduce_sum(domains: &mut [DomainSet], terms: &[(usize, i64)], total: i64) -> bool {
+    let mut change = true;
+    while change {
+        change = false;
+        let mut bounds = Vec::with_capacity(terms.len());
+        let (mut min, mut max) = (0, 0);
+        for &(v, w) in terms {
+            if domains[v].is_empty() {
+                return false;
+            }
+            let (l, h) = term_bounds(domains[v], w);
+            bounds.push((l, h));
+            min += l;
+            max += h;
+        }
+        if min > total || max < total {
+            return false;
+        }
+        for (&(v, w), &(l, h)) in terms.iter().zip(&bounds) {
+            for j in domains[v] {
+                let val = w * j as i64;
+                if min - l + val > total || max - h + val < total {
+                    domains[v].remove(j);
+                    change = true;
+                }
+            }
+            if domains[v].is_empty() {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+fn reduce_difference(domains: &mut [DomainSet], a: usize, b: usize, forbidden: DomainSet) -> bool {
+    let other = domains[b];
+    for j in domains[a] {
+        if !other.map(|k| j.abs_diff(k)).any(|d| !forbidden.contains(d)) {
+            domains[a].remove(j);
+        }
+    }
+    return !domains[a].is_empty();
+}
+
+fn reduce_consecutive(domains: &mut [DomainSet], vars: &[usize]) -> bool {
+    let mut all = DomainSet::empty();
+    for &v in vars {
+        all.add_all(domains[v]);
+    }
+    let (low, high) = match (all.get_any(), all.max()) {
+        (Some(low), Some(high)) => (low, high),
+        _ => return false,
+    };
+    let mut possible = DomainSet::empty();
+    for start in low..=high {
+        let window = DomainSet::range(start..(start + vars.len() as u32).min(64));
+        if vars.iter().all(|&v| !(domains[v] & window).is_empty()) {
+            possible.add_all(window);
+        }
+    }
+    for &v in vars {
+        domains[v].retain_all(possible);
+        if domains[v].is_empty() {
+            return false;
+        }
+    }
+    return true;
+}
+
+fn reduce_increasing(domains: &mut [DomainSet], vars: &[usize]) -> bool {
+    for pair in vars.windows(2) {
+        match domains[pair[0]].get_any() {
+            Some(low) => domains[pair[1]].remove_all(DomainSet::range(0..low + 1)),
+            None => return false,
+        }
+    }
+    for pair in vars.windows(2).rev() {
+        match domains[pair[1]].max() {
+            Some(high) => domains[pair[0]].retain_all(DomainSet::range(0..high)),
+            None => return false,
+        }
+    }
+    return vars.iter().all(|&v| !domains[v].is_empty());
+}
+
+fn reduce_table(domains: &mut [DomainSet], a: usize, b: usize, allowed: &[DomainSet]) -> bool {
+    let mut supported = DomainSet::empty();
+    for j in domains[a] {
+        let mut support = allowed
+            .get(j as usize)
+            .copied()
+            .unwrap_or(DomainSet::empty());
+        support.retain_all(domains[b]);
+        if support.is_empty() {
+            domains[a].remove(j);
+        } else {
+            supported.add_all(support);
+        }
+    }
+    domains[b].retain_all(supported);
+    return !domains[a].is_empty() && !domains[b].is_empty();
+}
+
+fn sandwich_supports(
+    domains: &[DomainSet],
+    vars: &[usize],
+    crusts: DomainSet,
+    total: i64,
+    supports: &mut [DomainSet],
+) -> bool {
+    let mut bounds = Vec::with_capacity(vars.len());
+    let (mut min, mut max) = (0, 0);
+    for &v in vars {
+        let (l, h) = term_bounds(domains[v].without_all(crusts), 1);
+        if l > h {
+            return false;
+        }
+        bounds.push((l + 1, h + 1));
+        min += l + 1;
+        max += h + 1;
+    }
+    if min > total || max < total {
+        return false;
+    }
+    for ((&v, &(l, h)), support) in vars.iter().zip(&bounds).zip(supports.iter_mut()) {
+        for j in domains[v].without_all(crusts) {
+            let val = j as i64 + 1;
+            if min - l + val <= total && max - h + val >= total {
+                support.add(j);
+            }
+        }
+    }
+    return true;
+}
+
+fn reduce_sandwich(
+    domains: &mut [DomainSet],
+    vars: &[usize],
+    (low, high): (u32, u32),
+    total: i64,
+) -> bool {
+    let crusts = DomainSet::singleton(low) | DomainSet::singleton(high);
+    let mut possible = vec![DomainSet::empty(); vars.len()];
+    for a in 0..vars.len() {
+        for b in a + 1..vars.len() {
+            for (first, last) in [(low, high), (high, low)] {
+                if domains[vars[a]].contains(first) && domains[vars[b]].contains(last) {
+                    let mut supports = vec![DomainSet::empty(); vars.len()];
+                    if sandwich_supports(
+                        domains,
+                        &vars[a + 1..b],
+                        crusts,
+                        total,
+                        &mut supports[a + 1..b],
+                    ) {
+                        for i in (0..a).chain(b + 1..vars.len()) {
+                            supports[i] = domains[vars[i]].without_all(crusts);
+                        }
+                        supports[a] = DomainSet::singleton(first);
+                        supports[b] = DomainSet::singleton(last);
+                        if supports.iter().all(|s| !s.is_empty()) {
+                            for (p, s) in possible.iter_mut().zip(supports) {
+                                p.add_all(s);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for (&v, &p) in vars.iter().zip(&possible) {
+        domains[v].retain_all(p);
+        if domains[v].is_empty() {
+            return false;
+        }
+    }
+    return true;
+}
+
+/// Removes all values that are not part of some accepted sequence of states. Starting from the
+/// initial state, each variable moves to the next state depending on its value.
+fn reduce_sequence<S, F, A>(
+    domains: &mut [DomainSet],
+    vars: &[usize],
+    init: S,
+    step: F,
+    accept: A,
+) -> bool
+where
+    S: Copy + Eq + Hash,
+    F: Fn(S, u32) -> Option<S>,
+    A: Fn(S) -> bool,
+{
+    let mut states = Vec::with_capacity(vars.len() + 1);
+    states.push(HashSet::from([init]));
+    for &v in vars {
+        let mut next = HashSet::new();
+        for &s in states.last().unwrap() {
+            for j in domains[v] {
+                if let Some(t) = step(s, j) {
+                    next.insert(t);
+                }
+            }
+        }
+        states.push(next);
+    }
+    let mut alive = states[vars.len()]
+        .iter()
+        .copied()
+        .filter(|&s| accept(s))
+        .collect::<HashSet<_>>();
+    let mut supports = vec![DomainSet::empty(); vars.len()];
+    for (i, &v) in vars.iter().enumerate().rev() {
+        let mut prev = HashSet::new();
+        for &s in &states[i] {
+            for j in domains[v] {
+                if step(s, j).map(|t| alive.contains(&t)).unwrap_or(false) {
+                    supports[i].add(j);
+                    prev.insert(s);
+                }
+            }
+        }
+        alive = prev;
+    }
+    if alive.is_empty() {
+        return false;
+    }
+    for (&v, &s) in vars.iter().zip(&supports) {
+        domains[v].retain_all(s);
+    }
+    return true;
+}
+
+/// The values are different, so a line with as many values as cells is a permutation of them and
+/// has to reach the largest value. This gives the usual deductions, such as the largest value next
+/// to a clue of one.
+fn reduce_skyscraper(domains: &mut [DomainSet], vars: &[usize], count: usize) -> bool {
+    if !reduce_all_different(domains, vars) {
+        return false;
+    }
+    let mut all = DomainSet::empty();
+    for &v in vars {
+        all.add_all(domains[v]);
+    }
+    let highest = (all.len() == vars.len()).then(|| all.max()).flatten();
+    reduce_sequence(
+        domains,
+        vars,
+        (None, 0),
+        |(max, seen), j| {
+            if max.map(|m| j > m).unwrap_or(true) {
+                Some((Some(j), seen + 1)).filter(|&(_, seen)| seen <= count)
+            } else {
+                Some((max, seen))
+            }
+        },
+        |(max, seen)| seen == count && (highest.is_none() || max == highest),
+    )
+}
+
+struct CardinalityMatching<'a> {
+    domains: &'a [DomainSet],
+    vars: &'a [usize],
+    bounds: &'a [(usize, usize)],
+    fixed: Option<(usize, u32)>,
+    assigned: Vec<Option<u32>>,
+    load: Vec<usize>,
+}
+
+impl<'a> CardinalityMatching<'a> {
+    fn domain(&self, i: usize) -> DomainSet {
+        match self.fixed {
+            Some((k, j)) if k == i => DomainSet::singleton(j),
+            _ => self.domains[self.vars[i]],
+        }
+    }
+
+    fn augment(&mut self, i: usize, lower: bool, visited: &mut [bool]) -> bool {
+        visited[i] = true;
+        for j in self.domain(i) {
+            let cap = match self.bounds.get(j as usize) {
+                Some(&(min, max)) => {
+                    if lower {
+                        min
+                    } else {
+                        max
+                    }
+                }
+                None => continue,
+            };
+            if self.load[j as usize] < cap {
+                self.load[j as usize] += 1;
+                self.assigned[i] = Some(j);
+                return true;
+            }
+        }
+        for j in self.domain(i) {
+            for k in 0..self.vars.len() {
+                if !visited[k] && self.assigned[k] == Some(j) && self.augment(k, lower, visited) {
+                    // k moved to another value, so the load of j is unchanged.
+                    self.assigned[i] = Some(j);
+                    return true;
+                }
+            }
+        }
+        return false;
+    }
+
+    /// First satisfies the lower bounds and then extends the assignment up to the upper bounds.
+    /// Augmenting never lowers the load of a value, so this finds an assignment if there is one.
+    fn solve(&mut self) -> bool {
+        for lower in [true, false] {
+            for i in 0..self.vars.len() {
+                if self.assigned[i].is_none() {
+                    let mut visited = vec![false; self.vars.len()];
+                    self.augment(i, lower, &mut visited);
+                }
+            }
+            if lower
+                && self
+                    .bounds
+                    .iter()
+                    .zip(&self.load)
+                    .any(|(&(min, _), &load)| load < min)
+            {
+                return false;
+            }
+        }
+        return self.assigned.iter().all(|a| a.is_some());
+    }
+}
+
+fn cardinality_assignment(
+    domains: &[DomainSet],
+    vars: &[usize],
+    bounds: &[(usize, usize)],
+    fixed: Option<(usize, u32)>,
+) -> Option<Vec<u32>> {
+    let mut matching = CardinalityMatching {
+        domains,
+        vars,
+        bounds,
+        fixed,
+        assigned: vec![None; vars.len()],
+        load: vec![0; bounds.len()],
+    };
+    if matching.solve() {
+        return Some(matching.assigned.into_iter().map(|a| a.unwrap()).collect());
+    } else {
+        return None;
+    }
+}
+
+fn reduce_global_cardinality(
+    domains: &mut [DomainSet],
+    vars: &[usize],
+    bounds: &[(usize, usize)],
+) -> bool {
+    let assignment = match cardinality_assignment(domains, vars, bounds, None) {
+        Some(assignment) => assignment,
+        None => return false,
+    };
+    for (i, &v) in vars.iter().enumerate() {
+        for j in domains[v] {
+            if j != assignment[i]
+                && cardinality_assignment(domains, vars, bounds, Some((i, j))).is_none()
+            {
+                domains[v].remove(j);
+            }
+        }
+    }
+    return true;
+}
@@ -0,0 +1,538 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::solver::{constraint::Constraint, domain::DomainSet, solver::Problem};
+
+/// Clauses over the values of the variables. Every value in the domain of a variable is a boolean
+/// variable, numbered from one in order of the variables and values. Auxiliary variables follow.
+struct Cnf {
+    domains: Vec<DomainSet>,
+    offsets: Vec<i64>,
+    variables: i64,
+    clauses: Vec<Vec<i64>>,
+}
+
+impl Cnf {
+    fn new(domains: &[DomainSet]) -> Self {
+        let mut offsets = Vec::with_capacity(domains.len());
+        let mut variables = 0;
+        for domain in domains {
+            offsets.push(variables + 1);
+            variables += domain.len() as i64;
+        }
+        return Cnf {
+            domains: domains.to_vec(),
+            offsets,
+            variables,
+            clauses: Vec::new(),
+        };
+    }
+
+    fn literal(&self, var: usize, value: u32) -> i64 {
+        let below = self.domains[var] & DomainSet::range(0..value);
+        return self.offsets[var] + below.len() as i64;
+    }
+
+    fn fresh(&mut self) -> i64 {
+        self.variables += 1;
+        return self.variables;
+    }
+
+    /// Every variable takes exactly one of its values.
+    fn encode_domains(&mut self) {
+        for var in 0..self.domains.len() {
+            let literals = self.domains[var]
+                .map(|value| self.literal(var, value))
+                .collect::<Vec<_>>();
+            for (i, &a) in literals.iter().enumerate() {
+                for &b in &literals[i + 1..] {
+                    self.clauses.push(vec![-a, -b]);
+                }
+            }
+            self.clauses.push(literals);
+        }
+    }
+
+    fn encode_all_different(&mut self, vars: &[usize]) {
+        for (i, &a) in vars.iter().enumerate() {
+            for &b in &vars[i + 1..] {
+                for value in self.domains[a] & self.domains[b] {
+                    self.clauses
+                        .push(vec![-self.literal(a, value), -self.literal(b, value)]);
+                }
+            }
+        }
+    }
+
+    /// Adds an auxiliary variable for every partial sum of the terms that can still reach the
+    /// total, which is implied by the previous partial sum and the value of the next term.
+    fn encode_sum(&mut self, terms: &[(usize, i64)], total: i64) {
+        if terms.iter().any(|&(var, _)| self.domains[var].is_empty()) {
+            // The domain clauses can not be satisfied already.
+            return;
+        }
+        let mut rest = vec![(0, 0); terms.len() + 1];
+        for (j, &(var, weight)) in terms.iter().enumerate().rev() {
+            let values = self.domains[var].map(|value| weight * value as i64);
+            let (min, max) = (values.clone().min().unwrap(), values.max().unwrap());
+            rest[j] = (rest[j + 1].0 + min, rest[j + 1].1 + max);
+        }
+        if total < rest[0].0 || total > rest[0].1 {
+            self.clauses.push(Vec::new());
+            return;
+        }
+        // The empty partial sum is always zero, so it needs no variable.
+        let mut sums = BTreeMap::from([(0, None::<i64>)]);
+        for (j, &(var, weight)) in terms.iter().enumerate() {
+            let last = j + 1 == terms.len();
+            let (min, max) = rest[j + 1];
+            let mut next = BTreeMap::new();
+            for (&sum, &known) in &sums {
+                for value in self.domains[var] {
+                    let new = sum + weight * value as i64;
+                    let mut clause = Vec::with_capacity(3);
+                    if let Some(known) = known {
+                        clause.push(-known);
+                    }
+                    clause.push(-self.literal(var, value));
+                    if new + min <= total && total <= new + max {
+                        if last {
+                            continue;
+                        }
+                        let literal = match next.get(&new) {
+                            Some(&literal) => literal,
+                            None => self.fresh(),
+                        };
+                        next.insert(new, literal);
+                        clause.push(literal);
+                    }
+                    self.clauses.push(clause);
+                }
+            }
+            sums = next
+                .into_iter()
+                .map(|(sum, literal)| (sum, Some(literal)))
+                .collect();
+        }
+    }
+
+    /// Follows the states of a sequence constraint through the variables, as `reduce_sequence`
+    /// does. Every state from which an accepted state can still be reached gets an auxiliary
+    /// variable, which is implied by the previous state and the value of the next variable.
+    fn encode_sequence<S, F, A>(&mut self, vars: &[usize], init: S, step: F, accept: A)
+    where
+        S: Copy + Ord,
+        F: Fn(S, u32) -> Option<S>,
+        A: Fn(S) -> bool,
+    {
+        let mut states = Vec::with_capacity(vars.len() + 1);
+        states.push(BTreeSet::from([init]));
+        for &var in vars {
+            let mut next = BTreeSet::new();
+            for &state in states.last().unwrap() {
+                for value in self.domains[var] {
+                    if let Some(state) = step(state, value) {
+                        next.insert(state);
+                    }
+                }
+            }
+            states.push(next);
+        }
+        let mut alive = vec![BTreeSet::new(); vars.len() + 1];
+        alive[vars.len()] = states[vars.len()]
+            .iter()
+            .copied()
+            .filter(|&state| accept(state))
+            .collect();
+        for (j, &var) in vars.iter().enumerate().rev() {
+            let reaching = states[j]
+                .iter()
+                .copied()
+                .filter(|&state| {
+                    self.domains[var].clone().any(|value| {
+                        step(state, value).is_some_and(|next| alive[j + 1].contains(&next))
+                    })
+                })
+                .collect();
+            alive[j] = reaching;
+        }
+        if !alive[0].contains(&init) {
+            self.clauses.push(Vec::new());
+            return;
+        }
+        // The initial state always holds, so it needs no variable.
+        let mut literals = BTreeMap::from([(init, None::<i64>)]);
+        for (j, &var) in vars.iter().enumerate() {
+            let last = j + 1 == vars.len();
+            let mut next = BTreeMap::new();
+            for (&state, &known) in &literals {
+                for value in self.domains[var] {
+                    let mut clause = Vec::with_capacity(3);
+                    if let Some(known) = known {
+                        clause.push(-known);
+                    }
+                    clause.push(-self.literal(var, value));
+                    match step(state, value).filter(|next| alive[j + 1].contains(next)) {
+                        Some(_) if last => continue,
+                        Some(state) => {
+                            let literal = *next.entry(state).or_insert_with(|| self.fresh());
+                            clause.push(literal);
+                        }
+                        None => {}
+                    }
+                    self.clauses.push(clause);
+                }
+            }
+            literals = next
+                .into_iter()
+                .map(|(state, literal)| (state, Some(literal)))
+                .collect();
+        }
+    }
+
+    /// Excludes every pair of values of the two variables that is not allowed.
+    fn encode_pairs<F>(&mut self, a: usize, b: usize, allowed: F)
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        for x in self.domains[a] {
+            for y in self.domains[b] {
+                if (a != b || x == y) && !allowed(x, y) {
+                    self.clauses
+                        .push(vec![-self.literal(a, x), -self.literal(b, y)]);
+                }
+            }
+        }
+    }
+
+    /// Every value is counted along the variables, the values without bounds are excluded.
+    fn encode_global_cardinality(&mut self, vars: &[usize], bounds: &[(usize, usize)]) {
+        // Values outside of all domains still need to meet their lower bound.
+        let mut all = DomainSet::range(0..bounds.len().min(64) as u32);
+        for &var in vars {
+            all.add_all(self.domains[var]);
+        }
+        for value in all {
+            match bounds.get(value as usize) {
+                Some(&(min, max)) => self.encode_sequence(
+                    vars,
+                    0,
+                    |count, j| {
+                        if j == value {
+                            Some(count + 1).filter(|&count| count <= max)
+                        } else {
+                            Some(count)
+                        }
+                    },
+                    |count| count >= min,
+                ),
+                None => {
+                    for &var in vars {
+                        if self.domains[var].contains(value) {
+                            self.clauses.push(vec![-self.literal(var, value)]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The state is the crust seen first, once there is one, the sum of the values since then,
+    /// and whether the second crust was seen as well.
+    fn encode_sandwich(&mut self, vars: &[usize], (low, high): (u32, u32), total: i64) {
+        let crusts = DomainSet::singleton(low) | DomainSet::singleton(high);
+        self.encode_sequence(
+            vars,
+            (None, 0, false),
+            |(first, sum, closed), j| match first {
+                None if crusts.contains(j) => Some((Some(j), 0, false)),
+                None => Some((None, 0, false)),
+                Some(_) if closed && crusts.contains(j) => None,
+                Some(first) if crusts.contains(j) => {
+                    let other = if first == low { high } else { low };
+                    Some((Some(first), sum, true)).filter(|_| j == other && sum == total)
+                }
+                Some(_) if closed => Some((first, sum, closed)),
+                Some(_) => Some((first, sum + j as i64 + 1, closed)).filter(|s| s.1 <= total),
+            },
+            |(_, _, closed)| closed,
+        );
+    }
+}
+
+impl Problem {
+    /// Encodes the problem as CNF in the DIMACS format, using a one-hot encoding of the domains.
+    /// Constraints on two variables exclude the pairs of values they forbid, the others follow
+    /// their states through the variables with auxiliary variables.
+    pub fn to_dimacs(&self) -> String {
+        let mut cnf = Cnf::new(self.domains());
+        cnf.encode_domains();
+        for constraint in self.constraints() {
+            match constraint {
+                Constraint::AllDifferent(vars) => cnf.encode_all_different(vars),
+                Constraint::Sum(terms, total) => cnf.encode_sum(terms, *total),
+                Constraint::ForbiddenDifference(a, b, forbidden) => {
+                    cnf.encode_pairs(*a, *b, |x, y| !forbidden.contains(x.abs_diff(y)))
+                }
+                Constraint::Consecutive(vars) => cnf.encode_sequence(
+                    vars,
+                    None,
+                    |range, j| match range {
+                        None => Some(Some((j, j))),
+                        Some((low, high)) => Some(Some((low.min(j), high.max(j))))
+                            .filter(|&range| range.map_or(0, |(l, h)| h - l) < vars.len() as u32),
+                    },
+                    |_| true,
+                ),
+                Constraint::Increasing(vars) => cnf.encode_sequence(
+                    vars,
+                    None,
+                    |last, j| match last {
+                        Some(last) if last >= j => None,
+                        _ => Some(Some(j)),
+                    },
+                    |_| true,
+                ),
+                Constraint::Table(a, b, allowed) => cnf.encode_pairs(*a, *b, |x, y| {
+                    allowed.get(x as usize).is_some_and(|set| set.contains(y))
+                }),
+                Constraint::Sandwich(vars, crusts, total) => {
+                    cnf.encode_sandwich(vars, *crusts, *total)
+                }
+                Constraint::Skyscraper(vars, count) => {
+                    cnf.encode_all_different(vars);
+                    cnf.encode_sequence(
+                        vars,
+                        (None, 0),
+                        |(max, seen), j| match max {
+                            Some(max) if max > j => Some((Some(max), seen)),
+                            _ => Some((Some(j), seen + 1)).filter(|&(_, seen)| seen <= *count),
+                        },
+                        |(_, seen)| seen == *count,
+                    );
+                }
+                Constraint::GlobalCardinality(vars, bounds) => {
+                    cnf.encode_global_cardinality(vars, bounds)
+                }
+            }
+        }
+        let mut res = format!("p cnf {} {}\n", cnf.variables, cnf.clauses.len());
+        for clause in &cnf.clauses {
+            for literal in clause {
+                write!(res, "{} ", literal).unwrap();
+            }
+            res.push_str("0\n");
+        }
+        return res;
+    }
+
+    /// Reads the model found by a SAT solver for the formula of `to_dimacs`, either in the output
+    /// format of the SAT competition or as a plain list of literals.
+    pub fn decode_dimacs_model(&self, model: &str) -> Option<Vec<u32>> {
+        let cnf = Cnf::new(self.domains());
+        let mut assigned = vec![false; cnf.variables as usize + 1];
+        for line in model.lines().map(str::trim) {
+            if line.starts_with('c') || line.starts_with('s') {
+                continue;
+            }
+            for token in line.trim_start_matches('v').split_whitespace() {
+                let literal = token.parse::<i64>().ok()?;
+                if 0 < literal && literal <= cnf.variables {
+                    assigned[literal as usize] = true;
+                }
+            }
+        }
+        return self
+            .domains()
+            .iter()
+            .enumerate()
+            .map(|(var, &domain)| {
+                domain
+                    .into_iter()
+                    .find(|&value| assigned[cnf.literal(var, value) as usize])
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> (usize, Vec<Vec<i64>>) {
+        let mut lines = text.lines();
+        let header = lines.next().unwrap().split_whitespace().collect::<Vec<_>>();
+        let clauses = lines
+            .map(|line| {
+                let mut clause = line
+                    .split_whitespace()
+                    .map(|l| l.parse().unwrap())
+                    .collect::<Vec<i64>>();
+                assert_eq!(clause.pop(), Some(0));
+                return clause;
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(clauses.len(), header[3].parse::<usize>().unwrap());
+        return (header[2].parse().unwrap(), clauses);
+    }
+
+    /// A plain DPLL search, which is enough for the few auxiliary variables of small problems.
+    fn satisfy(clauses: &[Vec<i64>], assigned: &mut Vec<Option<bool>>) -> bool {
+        let value = |assigned: &[Option<bool>], l: i64| {
+            assigned[l.unsigned_abs() as usize].map(|v| v == (l > 0))
+        };
+        loop {
+            let mut unit = None;
+            for clause in clauses {
+                if clause.iter().any(|&l| value(assigned, l) == Some(true)) {
+                    continue;
+                }
+                let mut open = clause.iter().filter(|&&l| value(assigned, l).is_none());
+                match (open.next(), open.next()) {
+                    (None, _) => return false,
+                    (Some(&l), None) => unit = Some(l),
+                    _ => {}
+                }
+            }
+            match unit {
+                Some(l) => assigned[l.unsigned_abs() as usize] = Some(l > 0),
+                None => break,
+            }
+        }
+        let var = match (1..assigned.len()).find(|&v| assigned[v].is_none()) {
+            Some(var) => var,
+            None => return true,
+        };
+        for choice in [true, false] {
+            let mut next = assigned.clone();
+            next[var] = Some(choice);
+            if satisfy(clauses, &mut next) {
+                *assigned = next;
+                return true;
+            }
+        }
+        return false;
+    }
+
+    /// Compares the formula with the constraint on every assignment of the domains.
+    fn check(domains: &[DomainSet], constraint: Constraint) {
+        let mut problem = Problem::empty();
+        for &domain in domains {
+            problem.add_variable(domain);
+        }
+        problem.add(constraint.clone());
+        let (variables, clauses) = parse(&problem.to_dimacs());
+        let cnf = Cnf::new(domains);
+        let mut values = domains
+            .iter()
+            .map(|d| d.get_any().unwrap())
+            .collect::<Vec<_>>();
+        loop {
+            let mut singletons = values
+                .iter()
+                .map(|&v| DomainSet::singleton(v))
+                .collect::<Vec<_>>();
+            let allowed =
+                constraint.reduce(&mut singletons) && singletons.iter().all(|d| !d.is_empty());
+            let mut assigned = vec![None; variables + 1];
+            for (var, &domain) in domains.iter().enumerate() {
+                for value in domain {
+                    assigned[cnf.literal(var, value) as usize] = Some(value == values[var]);
+                }
+            }
+            assert_eq!(
+                satisfy(&clauses, &mut assigned),
+                allowed,
+                "{:?} {:?}",
+                constraint,
+                values
+            );
+            if allowed {
+                let model = (1..=variables)
+                    .map(|v| {
+                        if assigned[v] == Some(true) {
+                            v.to_string()
+                        } else {
+                            format!("-{}", v)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                assert_eq!(
+                    problem.decode_dimacs_model(&format!("s SATISFIABLE\nv {} 0", model)),
+                    Some(values.clone())
+                );
+            }
+            // The next assignment, counting through the domains like an odometer.
+            let mut var = 0;
+            loop {
+                if var == values.len() {
+                    return;
+                }
+                match domains[var]
+                    .without_all(DomainSet::range(0..values[var] + 1))
+                    .get_any()
+                {
+                    Some(value) => {
+                        values[var] = value;
+                        break;
+                    }
+                    None => values[var] = domains[var].get_any().unwrap(),
+                }
+                var += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_every_constraint() {
+        let full = [DomainSet::range(0..4); 4];
+        let mut mixed = full;
+        mixed[1] = DomainSet::singleton(0) | DomainSet::singleton(3);
+        mixed[2] = DomainSet::range(1..3);
+        let vars = vec![0, 1, 2, 3];
+        for domains in [full, mixed] {
+            check(&domains, Constraint::AllDifferent(vars.clone()));
+            check(&domains, Constraint::Sum(vec![(0, 1), (1, 2), (3, -1)], 4));
+            check(
+                &domains,
+                Constraint::ForbiddenDifference(0, 1, DomainSet::singleton(1)),
+            );
+            check(&domains, Constraint::Consecutive(vars.clone()));
+            check(&domains, Constraint::Increasing(vec![0, 2, 3]));
+            let allowed = vec![
+                DomainSet::range(0..2),
+                DomainSet::empty(),
+                DomainSet::singleton(3),
+            ];
+            check(&domains, Constraint::Table(0, 1, allowed));
+            check(&domains, Constraint::Sandwich(vars.clone(), (0, 3), 3));
+            check(&domains, Constraint::Skyscraper(vars.clone(), 2));
+            check(
+                &domains,
+                Constraint::GlobalCardinality(vars.clone(), vec![(1, 2), (0, 1), (1, 1)]),
+            );
+        }
+    }
+
+    #[test]
+    fn stays_small_for_long_lines() {
+        let mut problem = Problem::empty();
+        let vars = (0..16)
+            .map(|_| problem.add_variable(DomainSet::range(0..16)))
+            .collect::<Vec<_>>();
+        problem.add(Constraint::Skyscraper(vars.clone(), 5));
+        problem.add(Constraint::GlobalCardinality(vars, vec![(1, 1); 16]));
+        assert!(problem.to_dimacs().len() < 1_000_000);
+    }
+
+    #[test]
+    fn rejects_incomplete_models() {
+        let mut problem = Problem::empty();
+        problem.add_variable(DomainSet::range(0..2));
+        problem.add_variable(DomainSet::range(0..2));
+        assert_eq!(problem.decode_dimacs_model("v 2 -3 4 0"), Some(vec![1, 1]));
+        assert_eq!(problem.decode_dimacs_model("v 1 -2 -3 -4 0"), None);
+        assert_eq!(problem.decode_dimacs_model("v x 0"), None);
+    }
+}
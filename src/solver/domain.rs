@@ -86,6 +86,14 @@ impl DomainSet {
         }
     }
 
+    pub fn max(&self) -> Option<u32> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(63 - self.bitset.leading_zeros())
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.bitset.count_ones() as usize
     }
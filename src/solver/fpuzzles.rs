@@ -0,0 +1,540 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::solver::domain::DomainSet;
+use crate::solver::links::ImportError;
+use crate::solver::sudoku::{
+    default_regions, diagonal_cells, digits_where, extra_regions, Cell, DotKind, Line, LineKind,
+    Puzzle, Side, Variant,
+};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FCell {
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub value: Value,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub given: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<i64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FCells {
+    pub cells: Vec<String>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub value: Value,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FCellRef {
+    pub cell: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FLines {
+    pub lines: Vec<Vec<String>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FArrow {
+    pub cells: Vec<String>,
+    pub lines: Vec<Vec<String>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FLittleKiller {
+    pub cell: String,
+    pub cells: Vec<String>,
+    pub direction: String,
+    pub value: Value,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FOutside {
+    pub cell: String,
+    pub value: Value,
+}
+
+/// The subset of the f-puzzles JSON format that maps onto the variants of this project. Unknown
+/// constraints are ignored when reading.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FPuzzle {
+    pub size: usize,
+    pub grid: Vec<Vec<FCell>>,
+    #[serde(rename = "diagonal+", skip_serializing_if = "std::ops::Not::not")]
+    pub diagonal_positive: bool,
+    #[serde(rename = "diagonal-", skip_serializing_if = "std::ops::Not::not")]
+    pub diagonal_negative: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub disjointgroups: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub nonconsecutive: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extraregion: Vec<FCells>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub odd: Vec<FCellRef>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub even: Vec<FCellRef>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub thermometer: Vec<FLines>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub palindrome: Vec<FLines>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renban: Vec<FLines>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub whispers: Vec<FLines>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub regionsumline: Vec<FLines>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub arrow: Vec<FArrow>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub killercage: Vec<FCells>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub littlekillersum: Vec<FLittleKiller>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sandwichsum: Vec<FOutside>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub difference: Vec<FCells>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ratio: Vec<FCells>,
+}
+
+/// Numbers are sometimes stored as strings, e.g. for cage totals.
+pub(crate) fn number(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().map(|n| n as u32),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parses a cell reference like `R1C2`. Rows and columns are numbered from one, so clues outside
+/// of the grid have a row or column of -1 or N.
+fn cell_ref(cell: &str) -> Option<(i64, i64)> {
+    let rest = cell.strip_prefix(['R', 'r'])?;
+    let (row, col) = rest.split_once(['C', 'c'])?;
+    return Some((row.parse::<i64>().ok()? - 1, col.parse::<i64>().ok()? - 1));
+}
+
+fn grid_cell<const N: usize>(cell: &str) -> Option<Cell> {
+    let (row, col) = cell_ref(cell)?;
+    if 0 <= row && row < N as i64 && 0 <= col && col < N as i64 {
+        return Some((row as usize, col as usize));
+    }
+    return None;
+}
+
+fn grid_cells<const N: usize>(cells: &[String]) -> Vec<Cell> {
+    cells.iter().filter_map(|c| grid_cell::<N>(c)).collect()
+}
+
+fn outside_clue<const N: usize>(cell: &str) -> Option<(Side, usize)> {
+    let (row, col) = cell_ref(cell)?;
+    let n = N as i64;
+    let (side, index) = match (row, col) {
+        (-1, c) => (Side::Top, c),
+        (r, -1) => (Side::Left, r),
+        (r, c) if r == n => (Side::Bottom, c),
+        (r, c) if c == n => (Side::Right, r),
+        _ => return None,
+    };
+    return Some((side, index as usize)).filter(|_| 0 <= index && index < n);
+}
+
+fn little_killer<const N: usize>(clue: &FLittleKiller) -> Option<Variant> {
+    let total = number(&clue.value)?;
+    let mut cells = grid_cells::<N>(&clue.cells);
+    cells.sort();
+    for side in [Side::Top, Side::Bottom, Side::Left, Side::Right] {
+        for index in 0..N {
+            for forward in [true, false] {
+                let mut diagonal = diagonal_cells::<N>(side, index, forward);
+                diagonal.sort();
+                if diagonal == cells {
+                    return Some(Variant::LittleKiller {
+                        side,
+                        index,
+                        forward,
+                        total,
+                    });
+                }
+            }
+        }
+    }
+    return None;
+}
+
+fn dots<const N: usize>(list: &[FCells], kind: DotKind, default: u32) -> Vec<Variant> {
+    list.iter()
+        .filter(|dot| number(&dot.value).unwrap_or(default) == default)
+        .filter_map(|dot| match grid_cells::<N>(&dot.cells)[..] {
+            [a, b] => Some(Variant::Dot {
+                kind,
+                cells: (a, b),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn from_fpuzzle<const N: usize>(puzzle: &FPuzzle) -> Result<Puzzle<N>, ImportError> {
+    if puzzle.size != N || puzzle.grid.len() != N {
+        return Err(ImportError::Size(puzzle.size));
+    }
+    let mut res = Puzzle::empty();
+    for (i, row) in puzzle.grid.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate().take(N) {
+            if cell.given {
+                res.sudoku[i][j] = number(&cell.value).filter(|&v| 1 <= v && v <= N as u32);
+            }
+            if let Some(region) = cell.region.filter(|&r| 0 <= r && r < N as i64) {
+                res.regions[i][j] = region as usize;
+            }
+        }
+    }
+    for (list, digits) in [
+        (&puzzle.odd, digits_where::<N>(|d| d % 2 == 1)),
+        (&puzzle.even, digits_where::<N>(|d| d % 2 == 0)),
+    ] {
+        for (r, c) in list.iter().filter_map(|c| grid_cell::<N>(&c.cell)) {
+            res.restrictions[r][c] = digits;
+        }
+    }
+    let variants = &mut res.variants;
+    if puzzle.diagonal_positive {
+        variants.push(Variant::ExtraRegion(
+            (0..N).map(|i| (N - 1 - i, i)).collect(),
+        ));
+    }
+    if puzzle.diagonal_negative {
+        variants.push(Variant::ExtraRegion((0..N).map(|i| (i, i)).collect()));
+    }
+    if puzzle.disjointgroups {
+        variants.push(Variant::DisjointGroups);
+    }
+    if puzzle.nonconsecutive {
+        variants.push(Variant::NonConsecutive);
+    }
+    for region in &puzzle.extraregion {
+        variants.push(Variant::ExtraRegion(grid_cells::<N>(&region.cells)));
+    }
+    for (list, kind) in [
+        (&puzzle.thermometer, LineKind::Thermometer),
+        (&puzzle.palindrome, LineKind::Palindrome),
+        (&puzzle.renban, LineKind::Renban),
+        (&puzzle.whispers, LineKind::GermanWhisper),
+        (&puzzle.regionsumline, LineKind::RegionSum),
+    ] {
+        for line in list.iter().flat_map(|l| &l.lines) {
+            let cells = grid_cells::<N>(line);
+            variants.push(Variant::Line(Line { kind, cells }));
+        }
+    }
+    for arrow in &puzzle.arrow {
+        let circle = grid_cells::<N>(&arrow.cells);
+        let mut cells = Vec::new();
+        for cell in arrow.lines.iter().flat_map(|l| grid_cells::<N>(l)) {
+            if !circle.contains(&cell) && !cells.contains(&cell) {
+                cells.push(cell);
+            }
+        }
+        variants.push(Variant::Arrow {
+            circle,
+            arrow: cells,
+        });
+    }
+    for cage in &puzzle.killercage {
+        let mut cells = grid_cells::<N>(&cage.cells);
+        cells.sort();
+        let total = number(&cage.value);
+        variants.push(Variant::Killer { cells, total });
+    }
+    variants.extend(puzzle.littlekillersum.iter().filter_map(little_killer::<N>));
+    for clue in &puzzle.sandwichsum {
+        if let (Some((side, index)), Some(total)) =
+            (outside_clue::<N>(&clue.cell), number(&clue.value))
+        {
+            variants.push(Variant::Sandwich { side, index, total });
+        }
+    }
+    variants.extend(dots::<N>(&puzzle.difference, DotKind::White, 1));
+    variants.extend(dots::<N>(&puzzle.ratio, DotKind::Black, 2));
+    return Ok(res);
+}
+
+fn cell_name((row, col): (i64, i64)) -> String {
+    format!("R{}C{}", row + 1, col + 1)
+}
+
+fn cell_names(cells: &[Cell]) -> Vec<String> {
+    cells
+        .iter()
+        .map(|&(r, c)| cell_name((r as i64, c as i64)))
+        .collect()
+}
+
+fn outside_name<const N: usize>(side: Side, index: usize) -> String {
+    let (n, i) = (N as i64, index as i64);
+    cell_name(match side {
+        Side::Top => (-1, i),
+        Side::Bottom => (n, i),
+        Side::Left => (i, -1),
+        Side::Right => (i, n),
+    })
+}
+
+fn lines(cells: &[Cell]) -> FLines {
+    FLines {
+        lines: vec![cell_names(cells)],
+    }
+}
+
+/// Variants without an f-puzzles counterpart are left out, apart from extra regions. `left_out`
+/// lists what is lost.
+pub fn to_fpuzzle<const N: usize>(puzzle: &Puzzle<N>) -> FPuzzle {
+    let mut res = FPuzzle {
+        size: N,
+        ..Default::default()
+    };
+    for (i, defaults) in default_regions::<N>().iter().enumerate() {
+        let mut row = Vec::with_capacity(N);
+        for (j, &default) in defaults.iter().enumerate() {
+            let value = puzzle.sudoku[i][j];
+            row.push(FCell {
+                value: value.map(Value::from).unwrap_or_default(),
+                given: value.is_some(),
+                region: Some(puzzle.regions[i][j] as i64)
+                    .filter(|&region| region as usize != default),
+            });
+            let cell = FCellRef {
+                cell: cell_name((i as i64, j as i64)),
+            };
+            if puzzle.restrictions[i][j] == digits_where::<N>(|d| d % 2 == 1) {
+                res.odd.push(cell);
+            } else if puzzle.restrictions[i][j] == digits_where::<N>(|d| d % 2 == 0) {
+                res.even.push(cell);
+            }
+        }
+        res.grid.push(row);
+    }
+    for variant in &puzzle.variants {
+        match variant {
+            Variant::Arrow { circle, arrow } => {
+                let mut line = circle.last().copied().into_iter().collect::<Vec<_>>();
+                line.extend(arrow);
+                res.arrow.push(FArrow {
+                    cells: cell_names(circle),
+                    lines: vec![cell_names(&line)],
+                });
+            }
+            Variant::Line(Line { kind, cells }) => match kind {
+                LineKind::Thermometer => res.thermometer.push(lines(cells)),
+                LineKind::Palindrome => res.palindrome.push(lines(cells)),
+                LineKind::Renban => res.renban.push(lines(cells)),
+                LineKind::GermanWhisper => res.whispers.push(lines(cells)),
+                LineKind::RegionSum => res.regionsumline.push(lines(cells)),
+            },
+            Variant::Sandwich { side, index, total } => res.sandwichsum.push(FOutside {
+                cell: outside_name::<N>(*side, *index),
+                value: Value::from(total.to_string()),
+            }),
+            Variant::LittleKiller {
+                side,
+                index,
+                forward,
+                total,
+            } => {
+                let cells = diagonal_cells::<N>(*side, *index, *forward);
+                let (dr, dc) = match (side, forward) {
+                    (Side::Top, _) | (Side::Bottom, _) => (
+                        if *side == Side::Top { 1 } else { -1 },
+                        if *forward { 1 } else { -1 },
+                    ),
+                    (Side::Left, _) | (Side::Right, _) => (
+                        if *forward { 1 } else { -1 },
+                        if *side == Side::Left { 1 } else { -1 },
+                    ),
+                };
+                let (r, c) = cells[0];
+                res.littlekillersum.push(FLittleKiller {
+                    cell: cell_name((r as i64 - dr, c as i64 - dc)),
+                    cells: cell_names(&cells),
+                    direction: format!(
+                        "{}{}",
+                        if dr > 0 { "D" } else { "U" },
+                        if dc > 0 { "R" } else { "L" }
+                    ),
+                    value: Value::from(total.to_string()),
+                });
+            }
+            Variant::DisjointGroups => res.disjointgroups = true,
+            Variant::NonConsecutive => res.nonconsecutive = true,
+            Variant::Dot { kind, cells } => {
+                let dot = FCells {
+                    cells: cell_names(&[cells.0, cells.1]),
+                    value: Value::Null,
+                };
+                match kind {
+                    DotKind::White => res.difference.push(dot),
+                    DotKind::Black => res.ratio.push(dot),
+                }
+            }
+            Variant::Killer { cells, total } => res.killercage.push(FCells {
+                cells: cell_names(cells),
+                value: total
+                    .map(|t| Value::from(t.to_string()))
+                    .unwrap_or_default(),
+            }),
+            Variant::ExtraRegion(_) | Variant::Windoku | Variant::CenterDot => {
+                for region in extra_regions::<N>(variant) {
+                    res.extraregion.push(FCells {
+                        cells: cell_names(&region),
+                        value: Value::Null,
+                    });
+                }
+            }
+            Variant::Skyscraper { .. } => {}
+        }
+    }
+    return res;
+}
+
+/// The parts of the puzzle that `to_fpuzzle` cannot express, to be shown next to the links.
+pub fn left_out<const N: usize>(puzzle: &Puzzle<N>) -> Vec<&'static str> {
+    let mut res = Vec::new();
+    let expressible = [
+        DomainSet::range(0..N as u32),
+        digits_where::<N>(|d| d % 2 == 1),
+        digits_where::<N>(|d| d % 2 == 0),
+    ];
+    if puzzle
+        .restrictions
+        .iter()
+        .flatten()
+        .any(|restriction| !expressible.contains(restriction))
+    {
+        res.push("digit restrictions other than odd and even");
+    }
+    if puzzle
+        .variants
+        .iter()
+        .any(|variant| matches!(variant, Variant::Skyscraper { .. }))
+    {
+        res.push("skyscraper clues");
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read<const N: usize>(json: &str) -> Result<Puzzle<N>, ImportError> {
+        let puzzle: FPuzzle = serde_json::from_str(json).unwrap();
+        return from_fpuzzle(&puzzle);
+    }
+
+    #[test]
+    fn reads_givens_and_cages() {
+        let mut grid = vec![vec!["{}"; 4]; 4];
+        grid[0][0] = "{\"value\":2,\"given\":true}";
+        grid[1][1] = "{\"value\":3}";
+        let grid = grid
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(
+            "{{\"size\":4,\"grid\":[{}],\"killercage\":[{{\"cells\":[\"R1C2\",\"R2C2\"],\"value\":\"7\"}}]}}",
+            grid
+        );
+        let puzzle = read::<4>(&json).unwrap();
+        assert_eq!(puzzle.sudoku[0][0], Some(2));
+        // Values that are not given are the solver state of f-puzzles.
+        assert_eq!(puzzle.sudoku[1][1], None);
+        assert_eq!(
+            puzzle.variants,
+            vec![Variant::Killer {
+                cells: vec![(0, 1), (1, 1)],
+                total: Some(7),
+            }]
+        );
+    }
+
+    #[test]
+    fn round_trips_puzzles() {
+        let mut puzzle = Puzzle::<9>::empty();
+        puzzle.sudoku[0][0] = Some(5);
+        puzzle.sudoku[8][8] = Some(9);
+        puzzle.regions[2][2] = 4;
+        puzzle.regions[3][3] = 0;
+        puzzle.restrictions[4][4] = digits_where::<9>(|d| d % 2 == 1);
+        puzzle.variants = vec![
+            Variant::DisjointGroups,
+            Variant::NonConsecutive,
+            Variant::Line(Line {
+                kind: LineKind::Renban,
+                cells: vec![(5, 0), (5, 1), (6, 1)],
+            }),
+            Variant::Arrow {
+                circle: vec![(7, 7)],
+                arrow: vec![(6, 6), (5, 5)],
+            },
+            Variant::Killer {
+                cells: vec![(0, 4), (0, 5)],
+                total: Some(9),
+            },
+            Variant::Sandwich {
+                side: Side::Left,
+                index: 3,
+                total: 12,
+            },
+            Variant::Dot {
+                kind: DotKind::White,
+                cells: ((1, 1), (1, 2)),
+            },
+        ];
+        let json = serde_json::to_string(&to_fpuzzle(&puzzle)).unwrap();
+        assert_eq!(read::<9>(&json), Ok(puzzle));
+    }
+
+    #[test]
+    fn rejects_wrong_grid_size() {
+        assert_eq!(
+            read::<9>("{\"size\":6,\"grid\":[]}"),
+            Err(ImportError::Size(6))
+        );
+        assert_eq!(
+            read::<9>("{\"size\":9,\"grid\":[]}"),
+            Err(ImportError::Size(9))
+        );
+    }
+
+    #[test]
+    fn lists_what_is_left_out() {
+        let mut puzzle = Puzzle::<4>::empty();
+        puzzle.restrictions[0][0] = digits_where::<4>(|d| d % 2 == 0);
+        assert!(left_out(&puzzle).is_empty());
+        puzzle.restrictions[1][1] = digits_where::<4>(|d| d <= 2);
+        puzzle.variants.push(Variant::Skyscraper {
+            side: Side::Top,
+            index: 0,
+            count: 2,
+        });
+        assert_eq!(
+            left_out(&puzzle),
+            [
+                "digit restrictions other than odd and even",
+                "skyscraper clues"
+            ]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::solver::domain::DomainSet;
+use crate::solver::fpuzzles::{from_fpuzzle, to_fpuzzle, FPuzzle};
+use crate::solver::lzstring::{compress_to_base64, decompress_from_base64};
+use crate::solver::sudoku::{
+    default_regions, parse_sudoku, Cell, ParseError, Puzzle, SudokuFormat, SudokuText, Variant,
+};
+use crate::solver::sudokupad;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportError {
+    Decompress,
+    Json(String),
+    Size(usize),
+    Parse(ParseError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Decompress => write!(f, "unknown puzzle link"),
+            ImportError::Json(err) => write!(f, "invalid puzzle data: {}", err),
+            ImportError::Size(size) => write!(f, "unsupported grid size {}", size),
+            ImportError::Parse(err) => write!(f, "invalid givens: {}", err),
+        }
+    }
+}
+
+fn percent_decode(data: &str) -> String {
+    let bytes = data.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = data
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                res.push(byte);
+                i += 3;
+            }
+            // Form encoding turns the plus signs of base64 into spaces.
+            (b' ', _) => {
+                res.push(b'+');
+                i += 1;
+            }
+            (byte, _) => {
+                res.push(byte);
+                i += 1;
+            }
+        }
+    }
+    return String::from_utf8_lossy(&res).into_owned();
+}
+
+/// The compressed puzzle inside of a link. Links can also be pasted without the address.
+fn link_data(link: &str) -> &str {
+    let link = link.trim();
+    for key in ["load=", "puzzle="] {
+        if let Some(i) = link.find(key) {
+            return link[i + key.len()..].split(['&', '#']).next().unwrap_or("");
+        }
+    }
+    for prefix in ["/scl", "/fpuzzles"] {
+        if let Some(i) = link.find(prefix) {
+            return link[i + 1..].split(['?', '#']).next().unwrap_or("");
+        }
+    }
+    return link;
+}
+
+/// Reads f-puzzles links (`?load=`) and SudokuPad links, which contain either SudokuPad data
+/// (prefixed with `scl`) or f-puzzles data (prefixed with `fpuzzles`).
+pub fn import_link<const N: usize>(link: &str) -> Result<Puzzle<N>, ImportError> {
+    let data = percent_decode(link_data(link));
+    if let Some(scl) = data.strip_prefix("scl") {
+        let json = decompress_from_base64(scl).ok_or(ImportError::Decompress)?;
+        return sudokupad::from_json(&json);
+    }
+    let data = data.strip_prefix("fpuzzles").unwrap_or(&data);
+    let json = decompress_from_base64(data).ok_or(ImportError::Decompress)?;
+    let puzzle: FPuzzle =
+        serde_json::from_str(&json).map_err(|e| ImportError::Json(e.to_string()))?;
+    return from_fpuzzle(&puzzle);
+}
+
+/// The compressed f-puzzles data, escaped so that it survives in a link.
+fn export_data<const N: usize>(puzzle: &Puzzle<N>) -> String {
+    let json = serde_json::to_string(&to_fpuzzle(puzzle)).unwrap_or_default();
+    return compress_to_base64(&json)
+        .replace('+', "%2B")
+        .replace('/', "%2F")
+        .replace('=', "%3D");
+}
+
+pub fn fpuzzles_link<const N: usize>(puzzle: &Puzzle<N>) -> String {
+    return format!("https://www.f-puzzles.com/?load={}", export_data(puzzle));
+}
+
+/// SudokuPad reads f-puzzles data as well, which keeps the variants it knows about.
+pub fn sudokupad_link<const N: usize>(puzzle: &Puzzle<N>) -> String {
+    return format!("https://sudokupad.app/fpuzzles{}", export_data(puzzle));
+}
+
+/// The compact form of a puzzle in the links of this application. Only the parts that differ
+/// from an empty puzzle are included.
+#[derive(Serialize, Deserialize)]
+struct SharedPuzzle {
+    givens: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    restrictions: Vec<(Cell, DomainSet)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    regions: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    variants: Vec<Variant>,
+}
+
+fn in_grid<const N: usize>(variant: &Variant) -> bool {
+    let cell = |&(r, c): &Cell| r < N && c < N;
+    if let Some((_, index)) = variant.outside_clue() {
+        return index < N;
+    }
+    match variant {
+        Variant::Arrow { circle, arrow } => circle.iter().chain(arrow).all(cell),
+        Variant::Line(line) => line.cells.iter().all(cell),
+        Variant::ExtraRegion(cells) | Variant::Killer { cells, .. } => cells.iter().all(cell),
+        Variant::Dot { cells: (a, b), .. } => cell(a) && cell(b),
+        _ => true,
+    }
+}
+
+pub fn encode_puzzle<const N: usize>(puzzle: &Puzzle<N>) -> String {
+    let mut shared = SharedPuzzle {
+        givens: SudokuText {
+            sudoku: &puzzle.sudoku,
+            format: SudokuFormat::Line,
+        }
+        .to_string(),
+        restrictions: Vec::new(),
+        regions: Vec::new(),
+        variants: puzzle.variants.clone(),
+    };
+    let default = DomainSet::range(0..N as u32);
+    for (i, row) in puzzle.restrictions.iter().enumerate() {
+        for (j, &restriction) in row.iter().enumerate() {
+            if restriction != default {
+                shared.restrictions.push(((i, j), restriction));
+            }
+        }
+    }
+    if puzzle.regions != default_regions() {
+        shared.regions = puzzle.regions.iter().flatten().copied().collect();
+    }
+    return compress_to_base64(&serde_json::to_string(&shared).unwrap_or_default());
+}
+
+/// Reads puzzles written by `encode_puzzle`. Variants outside of the grid are dropped.
+pub fn decode_puzzle<const N: usize>(data: &str) -> Result<Puzzle<N>, ImportError> {
+    let json = decompress_from_base64(data).ok_or(ImportError::Decompress)?;
+    let shared: SharedPuzzle =
+        serde_json::from_str(&json).map_err(|e| ImportError::Json(e.to_string()))?;
+    let mut res = Puzzle::empty();
+    res.sudoku = parse_sudoku::<N>(&shared.givens).map_err(ImportError::Parse)?;
+    for ((r, c), restriction) in shared.restrictions {
+        if r < N && c < N {
+            res.restrictions[r][c] = restriction;
+        }
+    }
+    if shared.regions.len() == N * N {
+        for (i, &region) in shared.regions.iter().enumerate() {
+            res.regions[i / N][i % N] = region;
+        }
+    }
+    res.variants = shared.variants.into_iter().filter(in_grid::<N>).collect();
+    return Ok(res);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::sudoku::DotKind;
+
+    #[test]
+    fn reads_both_link_kinds() {
+        let json = "{\"size\":4,\"grid\":[[{\"value\":1,\"given\":true},{},{},{}],[{},{},{},{}],[{},{},{},{}],[{},{},{},{}]]}";
+        let data = compress_to_base64(json);
+        for link in [
+            format!(
+                "https://www.f-puzzles.com/?load={}",
+                data.replace('+', "%2B")
+            ),
+            format!("https://sudokupad.app/fpuzzles{}", data),
+        ] {
+            assert_eq!(import_link::<4>(&link).unwrap().sudoku[0][0], Some(1));
+        }
+        let scl = compress_to_base64("{\"cells\":[[{},{\"value\":2}],[{},{}]]}");
+        let link = format!("https://sudokupad.app/scl{}", scl);
+        assert_eq!(import_link::<2>(&link).unwrap().sudoku[0][1], Some(2));
+    }
+
+    /// A 9x9 link in the shortened form of SudokuPad, with bare and short keys.
+    #[test]
+    fn reads_shortened_sudokupad_links() {
+        let mut rows = vec![vec!["{}"; 9]; 9];
+        rows[0][0] = "{v:5}";
+        rows[8][8] = "{v:\"9\"}";
+        let cells = rows
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(
+            "{{ce:[{}],ca:[{{ce:[[1,1],[1,2],[2,1]],v:\"12\"}}],\
+             ov:[{{c:[4.5,4],w:0.3,h:0.3,r:1,ba:\"#000000\"}}]}}",
+            cells
+        );
+        let link = format!("https://sudokupad.app/scl{}", compress_to_base64(&json));
+        let puzzle = import_link::<9>(&link).unwrap();
+        assert_eq!(puzzle.sudoku[0][0], Some(5));
+        assert_eq!(puzzle.sudoku[8][8], Some(9));
+        assert_eq!(
+            puzzle.variants,
+            vec![
+                Variant::Killer {
+                    cells: vec![(1, 1), (1, 2), (2, 1)],
+                    total: Some(12),
+                },
+                Variant::Dot {
+                    kind: DotKind::Black,
+                    cells: ((4, 3), (4, 4)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_exported_links() {
+        let mut puzzle = Puzzle::<9>::empty();
+        puzzle.sudoku[4][2] = Some(7);
+        puzzle.variants.push(Variant::Killer {
+            cells: vec![(0, 0), (1, 0)],
+            total: Some(3),
+        });
+        assert_eq!(
+            import_link::<9>(&fpuzzles_link(&puzzle)),
+            Ok(puzzle.clone())
+        );
+        assert_eq!(import_link::<9>(&sudokupad_link(&puzzle)), Ok(puzzle));
+    }
+
+    #[test]
+    fn escapes_exported_links() {
+        let mut puzzle = Puzzle::<9>::empty();
+        puzzle.sudoku[0][0] = Some(1);
+        let data = compress_to_base64(&serde_json::to_string(&to_fpuzzle(&puzzle)).unwrap());
+        assert!(data.contains(['+', '/', '=']));
+        for link in [fpuzzles_link(&puzzle), sudokupad_link(&puzzle)] {
+            let (_, data) = link.rsplit_once(['=', '/']).unwrap();
+            assert!(!data.contains(['+', '/', '=']), "{}", link);
+            assert_eq!(import_link::<9>(&link), Ok(puzzle.clone()));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        assert_eq!(
+            import_link::<9>("https://www.f-puzzles.com/?load=!!!"),
+            Err(ImportError::Decompress)
+        );
+        let data = compress_to_base64("not json");
+        assert!(matches!(import_link::<9>(&data), Err(ImportError::Json(_))));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{HashMap, HashSet};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+struct BitReader {
+    values: Vec<u32>,
+    index: usize,
+    position: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut res = 0;
+        for i in 0..bits {
+            let value = *self.values.get(self.index)?;
+            if value & self.position != 0 {
+                res |= 1 << i;
+            }
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = 32;
+                self.index += 1;
+            }
+        }
+        return Some(res);
+    }
+}
+
+/// The base64 variant of lz-string, which f-puzzles and SudokuPad use for their links.
+pub fn decompress_from_base64(input: &str) -> Option<String> {
+    let values = input
+        .bytes()
+        .filter(|&b| b != b'=')
+        .map(|b| BASE64.iter().position(|&c| c == b).map(|v| v as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut reader = BitReader {
+        values,
+        index: 0,
+        position: 32,
+    };
+    let mut dictionary: Vec<Vec<u16>> = vec![Vec::new(); 3];
+    let mut enlarge_in = 4;
+    let mut num_bits = 3;
+    let first = match reader.read(2)? {
+        0 => reader.read(8)? as u16,
+        1 => reader.read(16)? as u16,
+        _ => return Some(String::new()),
+    };
+    dictionary.push(vec![first]);
+    let mut w = vec![first];
+    let mut result = w.clone();
+    loop {
+        let mut c = reader.read(num_bits)? as usize;
+        match c {
+            0 | 1 => {
+                let bits = if c == 0 { 8 } else { 16 };
+                dictionary.push(vec![reader.read(bits)? as u16]);
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return Some(String::from_utf16_lossy(&result)),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+        let entry = if c < dictionary.len() {
+            dictionary[c].clone()
+        } else if c == dictionary.len() {
+            let mut entry = w.clone();
+            entry.push(w[0]);
+            entry
+        } else {
+            return None;
+        };
+        result.extend(&entry);
+        let mut next = w;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        w = entry;
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+struct Compressor {
+    res: String,
+    value: u32,
+    position: u32,
+    dictionary: HashMap<Vec<u16>, u32>,
+    to_create: HashSet<Vec<u16>>,
+    enlarge_in: u32,
+    num_bits: u32,
+}
+
+impl Compressor {
+    /// Writes the lowest bits of the value, starting with the least significant one.
+    fn write(&mut self, mut value: u32, bits: u32) {
+        for _ in 0..bits {
+            self.value = (self.value << 1) | (value & 1);
+            value >>= 1;
+            if self.position == 5 {
+                self.res.push(BASE64[self.value as usize] as char);
+                self.position = 0;
+                self.value = 0;
+            } else {
+                self.position += 1;
+            }
+        }
+    }
+
+    fn count_entry(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.num_bits;
+            self.num_bits += 1;
+        }
+    }
+
+    fn emit(&mut self, w: &[u16]) {
+        if self.to_create.remove(w) {
+            if w[0] < 256 {
+                self.write(0, self.num_bits);
+                self.write(w[0] as u32, 8);
+            } else {
+                self.write(1, self.num_bits);
+                self.write(w[0] as u32, 16);
+            }
+            self.count_entry();
+        } else {
+            self.write(self.dictionary[w], self.num_bits);
+        }
+        self.count_entry();
+    }
+}
+
+pub fn compress_to_base64(input: &str) -> String {
+    let mut compressor = Compressor {
+        res: String::new(),
+        value: 0,
+        position: 0,
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        enlarge_in: 2,
+        num_bits: 2,
+    };
+    let mut w = Vec::new();
+    for c in input.encode_utf16() {
+        if !compressor.dictionary.contains_key(&[c][..]) {
+            let next = compressor.dictionary.len() as u32 + 3;
+            compressor.dictionary.insert(vec![c], next);
+            compressor.to_create.insert(vec![c]);
+        }
+        let mut wc = w.clone();
+        wc.push(c);
+        if compressor.dictionary.contains_key(&wc) {
+            w = wc;
+        } else {
+            compressor.emit(&w);
+            let next = compressor.dictionary.len() as u32 + 3;
+            compressor.dictionary.insert(wc, next);
+            w = vec![c];
+        }
+    }
+    if !w.is_empty() {
+        compressor.emit(&w);
+    }
+    compressor.write(2, compressor.num_bits);
+    // The last character is padded with zeros, even if there are no bits left.
+    loop {
+        compressor.value <<= 1;
+        if compressor.position == 5 {
+            compressor
+                .res
+                .push(BASE64[compressor.value as usize] as char);
+            break;
+        }
+        compressor.position += 1;
+    }
+    let mut res = compressor.res;
+    while !res.len().is_multiple_of(4) {
+        res.push('=');
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compresses_known_vector() {
+        assert_eq!(compress_to_base64("hello"), "BYUwNmD2Q===");
+        assert_eq!(
+            decompress_from_base64("BYUwNmD2Q===").as_deref(),
+            Some("hello")
+        );
+    }
+
+    #[test]
+    fn round_trips_text() {
+        for text in [
+            "",
+            "a",
+            "abababababababab",
+            "{\"size\":9,\"grid\":[]}",
+            "äöü € 𝄞",
+        ] {
+            assert_eq!(
+                decompress_from_base64(&compress_to_base64(text)).as_deref(),
+                Some(text)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(decompress_from_base64("not base64!"), None);
+        assert_eq!(decompress_from_base64("BYUw"), None);
+    }
+}
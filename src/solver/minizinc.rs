@@ -0,0 +1,231 @@
+use std::fmt::Write;
+
+use crate::solver::{constraint::Constraint, domain::DomainSet, solver::Problem};
+
+/// Writes a set as a range if it has no gaps, and as a set literal otherwise.
+fn set(domain: DomainSet) -> String {
+    match (domain.get_any(), domain.max()) {
+        (Some(low), Some(high)) if low < high && domain.len() as u32 == high - low + 1 => {
+            return format!("{}..{}", low, high);
+        }
+        _ => {
+            let values = domain.map(|value| value.to_string()).collect::<Vec<_>>();
+            return format!("{{{}}}", values.join(", "));
+        }
+    }
+}
+
+fn array(vars: &[usize]) -> String {
+    let names = vars
+        .iter()
+        .map(|var| format!("x{}", var))
+        .collect::<Vec<_>>();
+    return format!("[{}]", names.join(", "));
+}
+
+fn constraint(constraint: &Constraint) -> String {
+    match constraint {
+        Constraint::AllDifferent(vars) => {
+            return format!("alldifferent({})", array(vars));
+        }
+        Constraint::Sum(terms, total) => {
+            if terms.is_empty() {
+                return format!("0 = {}", total);
+            }
+            let terms = terms
+                .iter()
+                .map(|&(var, weight)| format!("{} * x{}", weight, var))
+                .collect::<Vec<_>>();
+            return format!("{} = {}", terms.join(" + "), total);
+        }
+        Constraint::ForbiddenDifference(a, b, forbidden) => {
+            return format!("not (abs(x{} - x{}) in {})", a, b, set(*forbidden));
+        }
+        Constraint::Consecutive(vars) => {
+            let s = array(vars);
+            return format!("max({}) - min({}) < {}", s, s, vars.len());
+        }
+        Constraint::Increasing(vars) => {
+            return format!("strictly_increasing({})", array(vars));
+        }
+        Constraint::Table(a, b, allowed) => {
+            let rows = allowed
+                .iter()
+                .enumerate()
+                .flat_map(|(j, values)| values.map(move |k| format!("{}, {}", j, k)))
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                return "false".to_owned();
+            }
+            return format!("table([x{}, x{}], [| {} |])", a, b, rows.join(" | "));
+        }
+        Constraint::Sandwich(vars, (low, high), total) => {
+            // Equal crusts have to appear twice, and then only one pair of positions matches.
+            let (low_count, high_count) = if low == high { (2, 2) } else { (1, 1) };
+            return format!(
+                "let {{ array[int] of var int: s = {} }} in \
+                 count(s, {}) = {} /\\ count(s, {}) = {} /\\ \
+                 sum(i, j in index_set(s) where i < j)(\
+                 bool2int((s[i] = {} /\\ s[j] = {}) \\/ (s[i] = {} /\\ s[j] = {})) * \
+                 sum(k in i + 1..j - 1)(s[k] + 1)) = {}",
+                array(vars),
+                low,
+                low_count,
+                high,
+                high_count,
+                low,
+                high,
+                high,
+                low,
+                total
+            );
+        }
+        Constraint::Skyscraper(vars, count) => {
+            return format!(
+                "let {{ array[int] of var int: s = {} }} in alldifferent(s) /\\ \
+                 sum(i in index_set(s))(bool2int(forall(j in 1..i - 1)(s[j] < s[i]))) = {}",
+                array(vars),
+                count
+            );
+        }
+        Constraint::GlobalCardinality(vars, bounds) => {
+            let values = (0..bounds.len()).map(|j| j.to_string());
+            let lows = bounds.iter().map(|&(low, _)| low.to_string());
+            let highs = bounds.iter().map(|&(_, high)| high.to_string());
+            return format!(
+                "global_cardinality_low_up_closed({}, [{}], [{}], [{}])",
+                array(vars),
+                values.collect::<Vec<_>>().join(", "),
+                lows.collect::<Vec<_>>().join(", "),
+                highs.collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+}
+
+impl Problem {
+    /// Writes the problem as a MiniZinc model, with one variable `x<i>` for every variable of the
+    /// problem and one constraint item for every constraint, in the order they were added. The
+    /// values are the raw values of the problem. FlatZinc for a solver is produced from this by
+    /// the MiniZinc compiler. An empty domain, which MiniZinc rejects, makes the model
+    /// unsatisfiable instead.
+    pub fn to_minizinc(&self) -> String {
+        let mut res = "include \"globals.mzn\";\n\n".to_owned();
+        for (var, &domain) in self.domains().iter().enumerate() {
+            if domain.is_empty() {
+                writeln!(res, "var int: x{};", var).unwrap();
+            } else {
+                writeln!(res, "var {}: x{};", set(domain), var).unwrap();
+            }
+        }
+        res.push('\n');
+        if self.domains().iter().any(|domain| domain.is_empty()) {
+            res.push_str("constraint false;\n");
+        }
+        for c in self.constraints() {
+            writeln!(res, "constraint {};", constraint(c)).unwrap();
+        }
+        res.push_str("\nsolve satisfy;\n");
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[u32]) -> DomainSet {
+        let mut res = DomainSet::empty();
+        for &value in values {
+            res.add(value);
+        }
+        return res;
+    }
+
+    #[test]
+    fn writes_every_constraint() {
+        let cases = [
+            (
+                Constraint::AllDifferent(vec![0, 1]),
+                "alldifferent([x0, x1])",
+            ),
+            (
+                Constraint::Sum(vec![(0, 2), (1, -1)], 3),
+                "2 * x0 + -1 * x1 = 3",
+            ),
+            (Constraint::Sum(vec![], 0), "0 = 0"),
+            (
+                Constraint::ForbiddenDifference(0, 1, values(&[1])),
+                "not (abs(x0 - x1) in {1})",
+            ),
+            (
+                Constraint::Consecutive(vec![0, 1, 2]),
+                "max([x0, x1, x2]) - min([x0, x1, x2]) < 3",
+            ),
+            (
+                Constraint::Increasing(vec![2, 0]),
+                "strictly_increasing([x2, x0])",
+            ),
+            (
+                Constraint::Table(
+                    0,
+                    1,
+                    vec![values(&[1, 2]), DomainSet::empty(), values(&[0])],
+                ),
+                "table([x0, x1], [| 0, 1 | 0, 2 | 2, 0 |])",
+            ),
+            (Constraint::Table(0, 1, vec![DomainSet::empty()]), "false"),
+            (
+                Constraint::Sandwich(vec![0, 1, 2], (0, 2), 4),
+                "let { array[int] of var int: s = [x0, x1, x2] } in \
+                 count(s, 0) = 1 /\\ count(s, 2) = 1 /\\ \
+                 sum(i, j in index_set(s) where i < j)(\
+                 bool2int((s[i] = 0 /\\ s[j] = 2) \\/ (s[i] = 2 /\\ s[j] = 0)) * \
+                 sum(k in i + 1..j - 1)(s[k] + 1)) = 4",
+            ),
+            (
+                Constraint::Skyscraper(vec![0, 1, 2], 2),
+                "let { array[int] of var int: s = [x0, x1, x2] } in alldifferent(s) /\\ \
+                 sum(i in index_set(s))(bool2int(forall(j in 1..i - 1)(s[j] < s[i]))) = 2",
+            ),
+            (
+                Constraint::GlobalCardinality(vec![0, 1, 2], vec![(1, 1), (0, 2)]),
+                "global_cardinality_low_up_closed([x0, x1, x2], [0, 1], [1, 0], [1, 2])",
+            ),
+        ];
+        for (c, expected) in cases {
+            assert_eq!(constraint(&c), expected);
+        }
+    }
+
+    #[test]
+    fn writes_the_model() {
+        let mut problem = Problem::empty();
+        problem.add_variable(DomainSet::range(1..4));
+        problem.add_variable(values(&[0, 2, 5]));
+        problem.add_variable(DomainSet::singleton(7));
+        problem.add(Constraint::AllDifferent(vec![0, 1, 2]));
+        assert_eq!(
+            problem.to_minizinc(),
+            "include \"globals.mzn\";\n\n\
+             var 1..3: x0;\n\
+             var {0, 2, 5}: x1;\n\
+             var {7}: x2;\n\n\
+             constraint alldifferent([x0, x1, x2]);\n\n\
+             solve satisfy;\n"
+        );
+    }
+
+    #[test]
+    fn writes_empty_domains_as_unsatisfiable() {
+        let mut problem = Problem::empty();
+        problem.add_variable(DomainSet::empty());
+        assert_eq!(
+            problem.to_minizinc(),
+            "include \"globals.mzn\";\n\n\
+             var int: x0;\n\n\
+             constraint false;\n\n\
+             solve satisfy;\n"
+        );
+    }
+}
@@ -1,3 +1,12 @@
+pub mod constraint;
+pub mod dimacs;
 pub mod domain;
+pub mod fpuzzles;
+pub mod links;
+pub mod lzstring;
+pub mod minizinc;
+pub mod multi;
 pub mod solver;
 pub mod sudoku;
+pub mod sudokupad;
+pub mod xcsp;
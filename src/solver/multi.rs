@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::solver::{domain::DomainSet, solver::Problem, sudoku::Cell};
+
+pub type MultiSudoku = Vec<Vec<Option<u32>>>;
+pub type MultiDomains = Vec<Vec<DomainSet>>;
+
+/// Several sudoku grids of size `N` placed on a common canvas. Cells in which the grids overlap
+/// are shared between them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MultiLayout<const N: usize> {
+    pub grids: Vec<Cell>,
+}
+
+impl MultiLayout<9> {
+    pub fn samurai() -> Self {
+        MultiLayout {
+            grids: vec![(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)],
+        }
+    }
+
+    pub fn butterfly() -> Self {
+        MultiLayout {
+            grids: vec![(0, 0), (0, 3), (3, 0), (3, 3)],
+        }
+    }
+
+    pub fn twodoku() -> Self {
+        MultiLayout {
+            grids: vec![(0, 0), (6, 6)],
+        }
+    }
+}
+
+impl<const N: usize> MultiLayout<N> {
+    pub fn height(&self) -> usize {
+        self.grids.iter().map(|&(r, _)| r + N).max().unwrap_or(0)
+    }
+
+    pub fn width(&self) -> usize {
+        self.grids.iter().map(|&(_, c)| c + N).max().unwrap_or(0)
+    }
+
+    pub fn box_size(&self) -> usize {
+        (N as f64).sqrt() as usize
+    }
+
+    /// The offsets of all grids that contain the given cell of the canvas.
+    pub fn grids_at(&self, (row, col): Cell) -> Vec<Cell> {
+        self.grids
+            .iter()
+            .copied()
+            .filter(|&(r, c)| r <= row && row < r + N && c <= col && col < c + N)
+            .collect()
+    }
+
+    pub fn contains(&self, cell: Cell) -> bool {
+        !self.grids_at(cell).is_empty()
+    }
+
+    pub fn empty_sudoku(&self) -> MultiSudoku {
+        vec![vec![None; self.width()]; self.height()]
+    }
+
+    pub fn default_domains(&self) -> MultiDomains {
+        self.sudoku_domains(&self.empty_sudoku())
+    }
+
+    pub fn sudoku_domains(&self, sudoku: &MultiSudoku) -> MultiDomains {
+        let mut res = vec![vec![DomainSet::empty(); self.width()]; self.height()];
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, domain) in row.iter_mut().enumerate() {
+                if self.contains((i, j)) {
+                    if let Some(v) = sudoku[i][j] {
+                        *domain = DomainSet::singleton(v - 1);
+                    } else {
+                        *domain = DomainSet::range(0..N as u32);
+                    }
+                }
+            }
+        }
+        return res;
+    }
+
+    /// The variable index of every cell of the canvas that is part of some grid.
+    fn variables(&self) -> Vec<Vec<Option<usize>>> {
+        let mut res = vec![vec![None; self.width()]; self.height()];
+        let mut next = 0;
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, var) in row.iter_mut().enumerate() {
+                if self.contains((i, j)) {
+                    *var = Some(next);
+                    next += 1;
+                }
+            }
+        }
+        return res;
+    }
+
+    pub fn create_problem(&self, domains: &MultiDomains) -> Problem {
+        let sr = self.box_size();
+        let vars = self.variables();
+        let mut prob = Problem::with_capacity(N * N * self.grids.len(), 3 * N * self.grids.len());
+        for i in 0..self.height() {
+            for j in 0..self.width() {
+                if vars[i][j].is_some() {
+                    prob.add_variable(domains[i][j]);
+                }
+            }
+        }
+        for &(r, c) in &self.grids {
+            let var = |i: usize, j: usize| vars[r + i][c + j].unwrap();
+            for i in 0..N {
+                let mut row = Vec::with_capacity(N);
+                let mut col = Vec::with_capacity(N);
+                let mut cell = Vec::with_capacity(N);
+                for j in 0..N {
+                    row.push(var(i, j));
+                    col.push(var(j, i));
+                    cell.push(var(sr * (i / sr) + j / sr, sr * (i % sr) + j % sr));
+                }
+                prob.add_constraint(row);
+                prob.add_constraint(col);
+                prob.add_constraint(cell);
+            }
+        }
+        return prob;
+    }
+
+    pub fn reshape_variables(&self, variables: Vec<u32>) -> MultiSudoku {
+        let mut res = self.empty_sudoku();
+        for (i, row) in self.variables().into_iter().enumerate() {
+            for (j, var) in row.into_iter().enumerate() {
+                if let Some(v) = var {
+                    res[i][j] = Some(variables[v] + 1);
+                }
+            }
+        }
+        return res;
+    }
+
+    pub fn flatten_domains(&self, domains: &MultiDomains) -> Vec<DomainSet> {
+        let mut res = Vec::with_capacity(N * N * self.grids.len());
+        for (i, row) in self.variables().into_iter().enumerate() {
+            for (j, var) in row.into_iter().enumerate() {
+                if var.is_some() {
+                    res.push(domains[i][j]);
+                }
+            }
+        }
+        return res;
+    }
+
+    pub fn reshape_domains(&self, domains: Vec<DomainSet>) -> MultiDomains {
+        let mut res = vec![vec![DomainSet::empty(); self.width()]; self.height()];
+        for (i, row) in self.variables().into_iter().enumerate() {
+            for (j, var) in row.into_iter().enumerate() {
+                if let Some(v) = var {
+                    res[i][j] = domains[v];
+                }
+            }
+        }
+        return res;
+    }
+}
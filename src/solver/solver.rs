@@ -1,16 +1,16 @@
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::SystemTime;
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, VecDeque},
     hash::{Hash, Hasher},
 };
 
-use crate::solver::domain::DomainSet;
+use crate::solver::{constraint::Constraint, domain::DomainSet};
 
 pub struct Problem {
     domains: Vec<DomainSet>,
     constrained: Vec<Vec<usize>>,
-    constraints: Vec<Vec<usize>>,
+    constraints: Vec<Constraint>,
 }
 
 impl Problem {
@@ -37,12 +37,24 @@ impl Problem {
     }
 
     pub fn add_constraint(&mut self, constraint: Vec<usize>) {
-        for &v in &constraint {
+        self.add(Constraint::AllDifferent(constraint));
+    }
+
+    pub fn add(&mut self, constraint: Constraint) {
+        for v in constraint.variables() {
             self.constrained[v].push(self.constraints.len());
         }
         self.constraints.push(constraint);
     }
 
+    pub fn domains(&self) -> &[DomainSet] {
+        &self.domains
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
     pub fn find_model(&self) -> Option<Vec<u32>> {
         let mut state = ProblemState::from_problem(self);
         if state.solve() {
@@ -112,6 +124,33 @@ impl Random {
     }
 }
 
+struct ChangeQueue {
+    queue: VecDeque<usize>,
+    queued: Vec<bool>,
+}
+
+impl ChangeQueue {
+    fn new(constraints: usize) -> Self {
+        ChangeQueue {
+            queue: VecDeque::with_capacity(constraints),
+            queued: vec![false; constraints],
+        }
+    }
+
+    fn push(&mut self, c: usize) {
+        if !self.queued[c] {
+            self.queued[c] = true;
+            self.queue.push_back(c);
+        }
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        let c = self.queue.pop_front()?;
+        self.queued[c] = false;
+        return Some(c);
+    }
+}
+
 #[derive(Clone)]
 struct ProblemState<'a> {
     problem: &'a Problem,
@@ -126,62 +165,22 @@ impl<'a> ProblemState<'a> {
         }
     }
 
-    fn is_constr_satisfiable(
-        &self,
-        constr: &[usize],
-        mut remove: DomainSet,
-        mut taken: DomainSet,
+    /// The domains before the reduction are kept in `old`, which is reused between the calls.
+    fn reduce_constraint(
+        &mut self,
+        constr: &Constraint,
+        changes: &mut ChangeQueue,
+        old: &mut Vec<DomainSet>,
     ) -> bool {
-        let mut left = constr.len() - taken.len();
-        let mut change = true;
-        while change && left > 0 {
-            change = false;
-            for (i, &w) in constr.iter().enumerate() {
-                if !taken.contains(i as u32) {
-                    let rem = self.domains[w].without_all(remove);
-                    if rem.is_singleton() {
-                        remove.add_all(rem);
-                        taken.add(i as u32);
-                        left -= 1;
-                        change = true;
-                    } else if rem.is_empty() {
-                        return false;
-                    }
-                }
-            }
-        }
-        let mut without = DomainSet::empty();
-        left = 0;
-        for (i, &w) in constr.iter().enumerate() {
-            if !taken.contains(i as u32) {
-                left += 1;
-                without.add_all(self.domains[w]);
-                if without.without_all(remove).len() < left {
-                    return false;
-                }
-            }
+        old.clear();
+        old.extend(constr.variables().map(|v| self.domains[v]));
+        if !constr.reduce(&mut self.domains) {
+            return false;
         }
-        return true;
-    }
-
-    fn reduce_constraint(&mut self, constr: &[usize], changes: &mut DomainSet) -> bool {
-        for (i, &v) in constr.iter().enumerate() {
-            let old = self.domains[v];
-            for j in self.domains[v] {
-                if !self.is_constr_satisfiable(
-                    constr,
-                    DomainSet::singleton(j),
-                    DomainSet::singleton(i as u32),
-                ) {
-                    self.domains[v].remove(j);
-                    if self.domains[v].is_empty() {
-                        return false;
-                    }
-                }
-            }
-            if self.domains[v] != old {
+        for (v, &d) in constr.variables().zip(old.iter()) {
+            if self.domains[v] != d {
                 for &c in &self.problem.constrained[v] {
-                    changes.add(c as u32);
+                    changes.push(c);
                 }
             }
         }
@@ -189,22 +188,21 @@ impl<'a> ProblemState<'a> {
     }
 
     fn reduce(&mut self, i: Option<usize>) -> bool {
-        let mut changes;
+        let mut changes = ChangeQueue::new(self.problem.constraints.len());
         if let Some(i) = i {
-            changes = DomainSet::empty();
             for &c in &self.problem.constrained[i] {
-                changes.add(c as u32);
+                changes.push(c);
             }
         } else {
-            changes = DomainSet::range(0..self.problem.constraints.len() as u32);
+            for c in 0..self.problem.constraints.len() {
+                changes.push(c);
+            }
         }
-        while !changes.is_empty() {
-            for i in changes.clone() {
-                changes.remove(i as u32);
-                if !self.reduce_constraint(&self.problem.constraints[i as usize], &mut changes) {
-                    self.domains = vec![DomainSet::empty(); self.domains.len()];
-                    return false;
-                }
+        let mut old = Vec::new();
+        while let Some(i) = changes.pop() {
+            if !self.reduce_constraint(&self.problem.constraints[i], &mut changes, &mut old) {
+                self.domains = vec![DomainSet::empty(); self.domains.len()];
+                return false;
             }
         }
         return true;
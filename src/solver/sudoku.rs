@@ -1,7 +1,130 @@
-use crate::solver::{domain::DomainSet, solver::Problem};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::solver::{constraint::Constraint, domain::DomainSet, solver::Problem};
 
 pub type Sudoku<const N: usize> = [[Option<u32>; N]; N];
 pub type SudokuDomains<const N: usize> = [[DomainSet; N]; N];
+/// The region of every cell. Regions are numbered from zero and replace the boxes of the grid.
+pub type SudokuRegions<const N: usize> = [[usize; N]; N];
+pub type Cell = (usize, usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineKind {
+    /// Adjacent digits differ by at least five (or half the size for other grids).
+    GermanWhisper,
+    /// The digits are a set of consecutive digits in any order.
+    Renban,
+    /// The line reads the same in both directions.
+    Palindrome,
+    /// The segments of the line in each box have the same sum.
+    RegionSum,
+    /// The digits increase starting from the bulb at the first cell.
+    Thermometer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DotKind {
+    /// The digits on both sides of the dot are consecutive.
+    White,
+    /// One of the digits is double the other.
+    Black,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+    pub kind: LineKind,
+    pub cells: Vec<Cell>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Variant {
+    /// The digits in the circle, read as a number, equal the sum of the digits on the arrow.
+    Arrow {
+        circle: Vec<Cell>,
+        arrow: Vec<Cell>,
+    },
+    Line(Line),
+    /// The digits between the 1 and the largest digit of the row or column sum to the total.
+    Sandwich {
+        side: Side,
+        index: usize,
+        total: u32,
+    },
+    /// The digits along the diagonal starting at the edge cell sum to the total. Digits may repeat.
+    LittleKiller {
+        side: Side,
+        index: usize,
+        forward: bool,
+        total: u32,
+    },
+    /// Seen from the side, the number of digits larger than all digits in front of them.
+    Skyscraper {
+        side: Side,
+        index: usize,
+        count: usize,
+    },
+    /// The cells contain no repeated digits.
+    ExtraRegion(Vec<Cell>),
+    /// Also known as hyper sudoku. Adds extra windows between the boxes.
+    Windoku,
+    /// Cells in the same position in each box contain different digits.
+    DisjointGroups,
+    /// The center cells of the boxes contain different digits.
+    CenterDot,
+    /// Orthogonally adjacent cells do not contain consecutive digits.
+    NonConsecutive,
+    /// A dot on the edge between two orthogonally adjacent cells.
+    Dot {
+        kind: DotKind,
+        cells: (Cell, Cell),
+    },
+    /// The digits in the cage do not repeat and sum to the total, if one is given.
+    Killer {
+        cells: Vec<Cell>,
+        total: Option<u32>,
+    },
+}
+
+impl Variant {
+    pub fn outside_clue(&self) -> Option<(Side, usize)> {
+        match self {
+            Variant::Sandwich { side, index, .. } => Some((*side, *index)),
+            Variant::LittleKiller { side, index, .. } => Some((*side, *index)),
+            Variant::Skyscraper { side, index, .. } => Some((*side, *index)),
+            _ => None,
+        }
+    }
+}
+
+/// A puzzle with all of its givens and rules, as it is exchanged with other applications.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Puzzle<const N: usize> {
+    pub sudoku: Sudoku<N>,
+    pub restrictions: SudokuDomains<N>,
+    pub regions: SudokuRegions<N>,
+    pub variants: Vec<Variant>,
+}
+
+impl<const N: usize> Puzzle<N> {
+    pub fn empty() -> Self {
+        Puzzle {
+            sudoku: empty_sudoku(),
+            restrictions: default_domains(),
+            regions: default_regions(),
+            variants: Vec::new(),
+        }
+    }
+}
 
 pub fn empty_sudoku<const N: usize>() -> Sudoku<N> {
     [[Option::<u32>::None; N]; N]
@@ -15,21 +138,61 @@ pub fn default_domains<const N: usize>() -> SudokuDomains<N> {
     [[DomainSet::range(0..N as u32); N]; N]
 }
 
-pub fn sudoku_domains<const N: usize>(sudoku: &Sudoku<N>) -> SudokuDomains<N> {
-    let mut res = empty_domains();
+pub fn sudoku_domains<const N: usize>(
+    sudoku: &Sudoku<N>,
+    restrictions: &SudokuDomains<N>,
+) -> SudokuDomains<N> {
+    let mut res = *restrictions;
     for i in 0..N {
         for j in 0..N {
             if let Some(v) = sudoku[i][j] {
-                res[i][j] = DomainSet::singleton(v - 1);
-            } else {
-                res[i][j] = DomainSet::range(0..N as u32);
+                res[i][j].retain_all(DomainSet::singleton(v - 1));
             }
         }
     }
     return res;
 }
 
-pub fn create_problem<const N: usize>(sudoku: &SudokuDomains<N>) -> Problem {
+pub fn digits_where<const N: usize>(pred: impl Fn(u32) -> bool) -> DomainSet {
+    let mut res = DomainSet::empty();
+    for d in 1..=N as u32 {
+        if pred(d) {
+            res.add(d - 1);
+        }
+    }
+    return res;
+}
+
+/// The cells of the row or column at the given index, starting from the given side.
+pub fn outside_cells<const N: usize>(side: Side, index: usize) -> Vec<Cell> {
+    match side {
+        Side::Top => (0..N).map(|r| (r, index)).collect(),
+        Side::Bottom => (0..N).rev().map(|r| (r, index)).collect(),
+        Side::Left => (0..N).map(|c| (index, c)).collect(),
+        Side::Right => (0..N).rev().map(|c| (index, c)).collect(),
+    }
+}
+
+/// The cells of the diagonal starting at the edge cell with the given index on the given side. The
+/// diagonal moves towards larger indices along the side if `forward` is set, and smaller otherwise.
+pub fn diagonal_cells<const N: usize>(side: Side, index: usize, forward: bool) -> Vec<Cell> {
+    let len = if forward { N - index } else { index + 1 };
+    let along = |k: usize| if forward { index + k } else { index - k };
+    (0..len)
+        .map(|k| match side {
+            Side::Top => (k, along(k)),
+            Side::Bottom => (N - 1 - k, along(k)),
+            Side::Left => (along(k), k),
+            Side::Right => (along(k), N - 1 - k),
+        })
+        .collect()
+}
+
+fn create_grid_problem<const N: usize>(
+    sudoku: &SudokuDomains<N>,
+    regions: Option<&SudokuRegions<N>>,
+    variants: &[Variant],
+) -> Problem {
     let mut prob = Problem::with_capacity(N * N, 3 * N);
     for row in sudoku {
         for cel in row {
@@ -39,22 +202,310 @@ pub fn create_problem<const N: usize>(sudoku: &SudokuDomains<N>) -> Problem {
     for i in 0..N {
         let mut row = Vec::with_capacity(N);
         let mut col = Vec::with_capacity(N);
-        let mut cell = Vec::with_capacity(N);
         for j in 0..N {
             row.push(N * i + j);
             col.push(N * j + i);
-            let sr = (N as f64).sqrt() as usize;
-            let ii = sr * (i / sr) + j / sr;
-            let jj = sr * (i % sr) + j % sr;
-            cell.push(N * ii + jj)
         }
         prob.add_constraint(row);
         prob.add_constraint(col);
-        prob.add_constraint(cell);
     }
+    if let Some(regions) = regions {
+        for region in region_cells(regions) {
+            prob.add_constraint(region.into_iter().map(cell_index::<N>).collect());
+        }
+    }
+    add_variants::<N>(&mut prob, regions.unwrap_or(&default_regions()), variants);
     return prob;
 }
 
+fn box_size<const N: usize>() -> usize {
+    (N as f64).sqrt() as usize
+}
+
+pub fn windoku_regions<const N: usize>() -> Vec<Vec<Cell>> {
+    let sr = box_size::<N>();
+    let starts = (0..sr - 1).map(|k| 1 + k * (sr + 1)).collect::<Vec<_>>();
+    let mut res = Vec::new();
+    for &r in &starts {
+        for &c in &starts {
+            res.push((0..N).map(|j| (r + j / sr, c + j % sr)).collect::<Vec<_>>());
+        }
+    }
+    return res;
+}
+
+fn disjoint_group<const N: usize>(r: usize, c: usize) -> Vec<Cell> {
+    let sr = box_size::<N>();
+    (0..N)
+        .map(|b| (sr * (b / sr) + r, sr * (b % sr) + c))
+        .collect()
+}
+
+pub fn disjoint_group_regions<const N: usize>() -> Vec<Vec<Cell>> {
+    let sr = box_size::<N>();
+    (0..N)
+        .map(|i| disjoint_group::<N>(i / sr, i % sr))
+        .collect()
+}
+
+pub fn center_dot_region<const N: usize>() -> Vec<Cell> {
+    let sr = box_size::<N>();
+    disjoint_group::<N>(sr / 2, sr / 2)
+}
+
+/// The additional regions without repeated digits introduced by the variant.
+pub fn extra_regions<const N: usize>(variant: &Variant) -> Vec<Vec<Cell>> {
+    match variant {
+        Variant::ExtraRegion(cells) => vec![cells.clone()],
+        Variant::Windoku => windoku_regions::<N>(),
+        Variant::DisjointGroups => disjoint_group_regions::<N>(),
+        Variant::CenterDot => vec![center_dot_region::<N>()],
+        Variant::Killer { cells, .. } => vec![cells.clone()],
+        _ => Vec::new(),
+    }
+}
+
+pub fn create_problem<const N: usize>(
+    sudoku: &SudokuDomains<N>,
+    regions: &SudokuRegions<N>,
+    variants: &[Variant],
+) -> Problem {
+    create_grid_problem(sudoku, Some(regions), variants)
+}
+
+pub fn create_latin_problem<const N: usize>(
+    sudoku: &SudokuDomains<N>,
+    variants: &[Variant],
+) -> Problem {
+    create_grid_problem(sudoku, None, variants)
+}
+
+fn cell_index<const N: usize>((row, col): Cell) -> usize {
+    N * row + col
+}
+
+fn box_index<const N: usize>((row, col): Cell) -> usize {
+    let sr = box_size::<N>();
+    sr * (row / sr) + col / sr
+}
+
+pub fn default_regions<const N: usize>() -> SudokuRegions<N> {
+    let mut res = [[0; N]; N];
+    for (i, row) in res.iter_mut().enumerate() {
+        for (j, region) in row.iter_mut().enumerate() {
+            *region = box_index::<N>((i, j));
+        }
+    }
+    return res;
+}
+
+pub fn region_cells<const N: usize>(regions: &SudokuRegions<N>) -> Vec<Vec<Cell>> {
+    let mut res = vec![Vec::new(); N];
+    for i in 0..N {
+        for j in 0..N {
+            if regions[i][j] < N {
+                res[regions[i][j]].push((i, j));
+            }
+        }
+    }
+    return res;
+}
+
+fn is_connected(cells: &[Cell]) -> bool {
+    let mut reached = Vec::with_capacity(cells.len());
+    reached.extend(cells.first().copied());
+    let mut i = 0;
+    while i < reached.len() {
+        let (r, c) = reached[i];
+        for &cell in cells {
+            let (nr, nc) = cell;
+            if r.abs_diff(nr) + c.abs_diff(nc) == 1 && !reached.contains(&cell) {
+                reached.push(cell);
+            }
+        }
+        i += 1;
+    }
+    return reached.len() == cells.len();
+}
+
+/// The regions that do not consist of exactly N orthogonally connected cells.
+pub fn invalid_regions<const N: usize>(regions: &SudokuRegions<N>) -> Vec<usize> {
+    region_cells(regions)
+        .iter()
+        .enumerate()
+        .filter(|(_, cells)| cells.len() != N || !is_connected(cells))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn digit_sum(terms: Vec<(usize, i64)>, total: i64) -> Constraint {
+    // Variables hold the digit minus one, so the total has to be corrected by the weights.
+    let weights = terms.iter().map(|&(_, w)| w).sum::<i64>();
+    Constraint::Sum(terms, total - weights)
+}
+
+fn add_line<const N: usize>(prob: &mut Problem, regions: &SudokuRegions<N>, line: &Line) {
+    let vars = line
+        .cells
+        .iter()
+        .map(|&c| cell_index::<N>(c))
+        .collect::<Vec<_>>();
+    match line.kind {
+        LineKind::GermanWhisper => {
+            let forbidden = DomainSet::range(0..(N as u32).div_ceil(2));
+            for pair in vars.windows(2) {
+                prob.add(Constraint::ForbiddenDifference(pair[0], pair[1], forbidden));
+            }
+        }
+        LineKind::Renban => {
+            prob.add(Constraint::AllDifferent(vars.clone()));
+            prob.add(Constraint::Consecutive(vars));
+        }
+        LineKind::Palindrome => {
+            let unequal = !DomainSet::singleton(0);
+            for i in 0..vars.len() / 2 {
+                prob.add(Constraint::ForbiddenDifference(
+                    vars[i],
+                    vars[vars.len() - 1 - i],
+                    unequal,
+                ));
+            }
+        }
+        LineKind::RegionSum => {
+            let region = |(r, c): Cell| regions[r][c];
+            let mut segments: Vec<Vec<usize>> = Vec::new();
+            for (i, &c) in line.cells.iter().enumerate() {
+                if i == 0 || region(c) != region(line.cells[i - 1]) {
+                    segments.push(Vec::new());
+                }
+                segments.last_mut().unwrap().push(vars[i]);
+            }
+            for segment in segments.iter().skip(1) {
+                let mut terms = Vec::with_capacity(segments[0].len() + segment.len());
+                terms.extend(segments[0].iter().map(|&v| (v, 1)));
+                terms.extend(segment.iter().map(|&v| (v, -1)));
+                prob.add(digit_sum(terms, 0));
+            }
+        }
+        LineKind::Thermometer => prob.add(Constraint::Increasing(vars)),
+    }
+}
+
+pub fn add_variant<const N: usize>(
+    prob: &mut Problem,
+    regions: &SudokuRegions<N>,
+    variant: &Variant,
+) {
+    match variant {
+        Variant::Arrow { circle, arrow } => {
+            let mut terms = Vec::with_capacity(circle.len() + arrow.len());
+            let mut weight = 1;
+            for &c in circle.iter().rev() {
+                terms.push((cell_index::<N>(c), weight));
+                weight *= 10;
+            }
+            for &c in arrow {
+                terms.push((cell_index::<N>(c), -1));
+            }
+            prob.add(digit_sum(terms, 0));
+        }
+        Variant::Line(line) => add_line::<N>(prob, regions, line),
+        Variant::Sandwich { side, index, total } => {
+            let vars = outside_cells::<N>(*side, *index)
+                .into_iter()
+                .map(cell_index::<N>)
+                .collect();
+            prob.add(Constraint::Sandwich(vars, (0, N as u32 - 1), *total as i64));
+        }
+        Variant::LittleKiller {
+            side,
+            index,
+            forward,
+            total,
+        } => {
+            let terms = diagonal_cells::<N>(*side, *index, *forward)
+                .into_iter()
+                .map(|c| (cell_index::<N>(c), 1))
+                .collect();
+            prob.add(digit_sum(terms, *total as i64));
+        }
+        Variant::Skyscraper { side, index, count } => {
+            let vars = outside_cells::<N>(*side, *index)
+                .into_iter()
+                .map(cell_index::<N>)
+                .collect();
+            prob.add(Constraint::Skyscraper(vars, *count));
+        }
+        Variant::NonConsecutive => {
+            let consecutive = DomainSet::singleton(1);
+            for r in 0..N {
+                for c in 0..N {
+                    if r + 1 < N {
+                        let (a, b) = (cell_index::<N>((r, c)), cell_index::<N>((r + 1, c)));
+                        prob.add(Constraint::ForbiddenDifference(a, b, consecutive));
+                    }
+                    if c + 1 < N {
+                        let (a, b) = (cell_index::<N>((r, c)), cell_index::<N>((r, c + 1)));
+                        prob.add(Constraint::ForbiddenDifference(a, b, consecutive));
+                    }
+                }
+            }
+        }
+        Variant::Dot {
+            kind,
+            cells: (first, second),
+        } => {
+            let (a, b) = (cell_index::<N>(*first), cell_index::<N>(*second));
+            match kind {
+                DotKind::White => {
+                    prob.add(Constraint::ForbiddenDifference(
+                        a,
+                        b,
+                        !DomainSet::singleton(1),
+                    ));
+                }
+                DotKind::Black => {
+                    let halves_and_doubles = (1..=N as u32)
+                        .map(|d| digits_where::<N>(|e| e == 2 * d || 2 * e == d))
+                        .collect();
+                    prob.add(Constraint::Table(a, b, halves_and_doubles));
+                }
+            }
+        }
+        Variant::Killer { cells, total } => {
+            let vars = cells
+                .iter()
+                .map(|&c| cell_index::<N>(c))
+                .collect::<Vec<_>>();
+            if let Some(total) = total {
+                prob.add(digit_sum(
+                    vars.iter().map(|&v| (v, 1)).collect(),
+                    *total as i64,
+                ));
+            }
+            prob.add_constraint(vars);
+        }
+        Variant::ExtraRegion(_)
+        | Variant::Windoku
+        | Variant::DisjointGroups
+        | Variant::CenterDot => {
+            for region in extra_regions::<N>(variant) {
+                prob.add_constraint(region.into_iter().map(cell_index::<N>).collect());
+            }
+        }
+    }
+}
+
+pub fn add_variants<const N: usize>(
+    prob: &mut Problem,
+    regions: &SudokuRegions<N>,
+    variants: &[Variant],
+) {
+    for variant in variants {
+        add_variant::<N>(prob, regions, variant);
+    }
+}
+
 pub fn reshape_variables<const N: usize>(variables: Vec<u32>) -> Sudoku<N> {
     let mut res = empty_sudoku();
     for (i, v) in variables.iter().enumerate() {
@@ -74,3 +525,315 @@ pub fn reshape_domains<const N: usize>(domains: Vec<DomainSet>) -> SudokuDomains
 pub fn flatten_domains<const N: usize>(domains: SudokuDomains<N>) -> Vec<DomainSet> {
     domains.into_iter().flatten().collect()
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// The number of cells that were read.
+    Length(usize),
+    Digit(char),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Length(len) => write!(f, "wrong number of cells: {}", len),
+            ParseError::Digit(c) => write!(f, "invalid digit '{}'", c),
+        }
+    }
+}
+
+/// Digits above 9 are written as letters, so 16x16 grids use 1-9 and A-G.
+pub fn digit_char(digit: u32) -> char {
+    return std::char::from_digit(digit, 36)
+        .unwrap_or('?')
+        .to_ascii_uppercase();
+}
+
+const BLANKS: &str = ".0_";
+
+/// Reads the givens from the one-line format, a grid with separators between the boxes, or a
+/// SadMan `.sdk` file. Blanks are `.`, `0` or `_`, and lines starting with `#` are comments.
+pub fn parse_sudoku<const N: usize>(text: &str) -> Result<Sudoku<N>, ParseError> {
+    let mut cells = Vec::with_capacity(N * N);
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            // Newer .sdk files store the solving state in sections after the puzzle.
+            if line.eq_ignore_ascii_case("[puzzle]") {
+                continue;
+            }
+            break;
+        }
+        for c in line.chars() {
+            if BLANKS.contains(c) {
+                cells.push(None);
+            } else if let Some(digit) = c.to_digit(36) {
+                if digit as usize > N {
+                    return Err(ParseError::Digit(c));
+                }
+                cells.push(Some(digit));
+            }
+        }
+    }
+    if cells.len() != N * N {
+        return Err(ParseError::Length(cells.len()));
+    }
+    let mut res = empty_sudoku();
+    for (i, cell) in cells.into_iter().enumerate() {
+        res[i / N][i % N] = cell;
+    }
+    return Ok(res);
+}
+
+/// Reads a collection of puzzles with one puzzle per line, in the one-line format. Anything after
+/// the puzzle on the same line, like a rating, is returned with it. Text that contains no such
+/// lines is read as a single puzzle.
+pub fn parse_sudoku_list<const N: usize>(text: &str) -> Vec<(Sudoku<N>, String)> {
+    let mut res = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(2, |c: char| c.is_whitespace() || c == ',' || c == ';');
+        if let Ok(sudoku) = parse_sudoku::<N>(parts.next().unwrap_or("")) {
+            let rest = parts.next().unwrap_or("").trim();
+            // Some collections list the solution next to the puzzle.
+            let rest = if parse_sudoku::<N>(rest).is_ok() {
+                ""
+            } else {
+                rest
+            };
+            res.push((sudoku, rest.to_owned()));
+        }
+    }
+    if res.is_empty() {
+        if let Ok(sudoku) = parse_sudoku::<N>(text) {
+            res.push((sudoku, String::new()));
+        }
+    }
+    return res;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SudokuFormat {
+    /// All cells on a single line.
+    Line,
+    /// One row per line, with separators between the boxes.
+    Grid,
+    /// The format of SadMan Software, one row per line.
+    Sdk,
+}
+
+/// Displays the givens of a sudoku in one of the text formats, with `.` for blanks.
+pub struct SudokuText<'a, const N: usize> {
+    pub sudoku: &'a Sudoku<N>,
+    pub format: SudokuFormat,
+}
+
+impl<const N: usize> fmt::Display for SudokuText<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sr = box_size::<N>();
+        let grid = self.format == SudokuFormat::Grid;
+        for (i, row) in self.sudoku.iter().enumerate() {
+            if grid && i > 0 && i % sr == 0 {
+                writeln!(f, "{}", vec!["-".repeat(2 * sr - 1); N / sr].join("-+-"))?;
+            }
+            for (j, cell) in row.iter().enumerate() {
+                if grid && j > 0 {
+                    write!(f, "{}", if j % sr == 0 { " | " } else { " " })?;
+                }
+                write!(f, "{}", cell.map(digit_char).unwrap_or('.'))?;
+            }
+            if self.format != SudokuFormat::Line && i + 1 < N {
+                writeln!(f)?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// The token of a cell that has no candidates left. An empty token would not be read back.
+const EMPTY_CANDIDATES: &str = "0";
+
+/// Reads a grid of candidates in the pencil-mark layout of HoDoKu and Simple Sudoku, where every
+/// cell lists its remaining digits. Border lines and `|` separators are skipped, and a cell without
+/// candidates is written as `0`.
+pub fn parse_candidates<const N: usize>(text: &str) -> Result<SudokuDomains<N>, ParseError> {
+    let mut cells = Vec::with_capacity(N * N);
+    for line in text.lines() {
+        if line.trim_start().starts_with('#') || !line.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        for token in line.split(|c: char| c.is_whitespace() || c == '|') {
+            if token.is_empty() {
+                continue;
+            }
+            let mut domain = DomainSet::empty();
+            if token == EMPTY_CANDIDATES {
+                cells.push(domain);
+                continue;
+            }
+            for c in token.chars() {
+                match c.to_digit(36) {
+                    Some(digit) if 1 <= digit && digit as usize <= N => domain.add(digit - 1),
+                    _ => return Err(ParseError::Digit(c)),
+                }
+            }
+            cells.push(domain);
+        }
+    }
+    if cells.len() != N * N {
+        return Err(ParseError::Length(cells.len()));
+    }
+    return Ok(reshape_domains(cells));
+}
+
+/// Displays candidates in the pencil-mark layout, with the columns aligned.
+pub struct CandidatesText<'a, const N: usize> {
+    pub domains: &'a SudokuDomains<N>,
+}
+
+impl<const N: usize> fmt::Display for CandidatesText<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sr = box_size::<N>();
+        let cells = self.domains.map(|row| {
+            row.map(|domain| {
+                if domain.is_empty() {
+                    EMPTY_CANDIDATES.to_owned()
+                } else {
+                    domain.map(|v| digit_char(v + 1)).collect::<String>()
+                }
+            })
+        });
+        let widths = (0..N)
+            .map(|j| cells.iter().map(|row| row[j].len()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let border = |left: char, middle: char, right: char| {
+            let boxes = widths
+                .chunks(sr)
+                .map(|box_widths| "-".repeat(box_widths.iter().map(|w| w + 1).sum::<usize>() + 2))
+                .collect::<Vec<_>>();
+            return format!("{}{}{}", left, boxes.join(&middle.to_string()), right);
+        };
+        writeln!(f, "{}", border('.', '.', '.'))?;
+        for (i, row) in cells.iter().enumerate() {
+            if i > 0 && i % sr == 0 {
+                writeln!(f, "{}", border(':', '+', ':'))?;
+            }
+            for (j, cell) in row.iter().enumerate() {
+                if j % sr == 0 {
+                    write!(f, "| ")?;
+                }
+                write!(f, "{:width$} ", cell, width = widths[j])?;
+                if j % sr == sr - 1 {
+                    write!(f, " ")?;
+                }
+            }
+            writeln!(f, "|")?;
+        }
+        write!(f, "{}", border('\'', '\'', '\''))?;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn round_trips_text_formats() {
+        let sudoku = parse_sudoku::<9>(LINE).unwrap();
+        assert_eq!(sudoku[0][0], Some(5));
+        assert_eq!(sudoku[0][2], None);
+        for format in [SudokuFormat::Line, SudokuFormat::Grid, SudokuFormat::Sdk] {
+            let text = SudokuText {
+                sudoku: &sudoku,
+                format,
+            }
+            .to_string();
+            assert_eq!(parse_sudoku::<9>(&text), Ok(sudoku));
+        }
+        let line = SudokuText {
+            sudoku: &sudoku,
+            format: SudokuFormat::Line,
+        };
+        assert_eq!(line.to_string(), LINE);
+    }
+
+    #[test]
+    fn reads_sdk_files_and_letters() {
+        let sdk = format!("#Amy\n[Puzzle]\n{}\n[State]\n123", LINE.replace('.', "0"));
+        assert_eq!(parse_sudoku::<9>(&sdk), parse_sudoku::<9>(LINE));
+        let sudoku = parse_sudoku::<16>(&format!("G{}", ".".repeat(255))).unwrap();
+        assert_eq!(sudoku[0][0], Some(16));
+    }
+
+    #[test]
+    fn rejects_wrong_length_and_digits() {
+        assert_eq!(parse_sudoku::<9>(&LINE[1..]), Err(ParseError::Length(80)));
+        assert_eq!(parse_sudoku::<9>(""), Err(ParseError::Length(0)));
+        let text = LINE.replacen('5', "A", 1);
+        assert_eq!(parse_sudoku::<9>(&text), Err(ParseError::Digit('A')));
+    }
+
+    #[test]
+    fn round_trips_candidates() {
+        let mut domains = default_domains::<9>();
+        domains[0][0] = DomainSet::singleton(4);
+        domains[3][7] = DomainSet::range(2..5);
+        domains[8][8] = DomainSet::singleton(0) | DomainSet::singleton(8);
+        let text = CandidatesText { domains: &domains }.to_string();
+        assert_eq!(parse_candidates::<9>(&text), Ok(domains));
+        // A contradiction, including a whole row without candidates.
+        domains[5] = [DomainSet::empty(); 9];
+        let text = CandidatesText { domains: &domains }.to_string();
+        assert_eq!(parse_candidates::<9>(&text), Ok(domains));
+    }
+
+    #[test]
+    fn rejects_wrong_candidates() {
+        let row = "123 456 789 ".repeat(3);
+        let text = [row.as_str(); 9].join("\n");
+        assert!(parse_candidates::<9>(&text).is_ok());
+        assert_eq!(
+            parse_candidates::<9>(&text.replacen('5', "0", 1)),
+            Err(ParseError::Digit('0'))
+        );
+        assert_eq!(
+            parse_candidates::<9>(&[row.as_str(); 8].join("\n")),
+            Err(ParseError::Length(72))
+        );
+    }
+
+    #[test]
+    fn reads_puzzle_lists() {
+        let solution =
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+        let text = format!(
+            "# collection\n{} 3.5 hard\n{},{}\n\nnot a puzzle\n",
+            LINE, LINE, solution
+        );
+        let sudoku = parse_sudoku::<9>(LINE).unwrap();
+        assert_eq!(
+            parse_sudoku_list::<9>(&text),
+            vec![(sudoku, "3.5 hard".to_owned()), (sudoku, String::new())]
+        );
+    }
+
+    #[test]
+    fn reads_single_grids_as_lists() {
+        let sudoku = parse_sudoku::<9>(LINE).unwrap();
+        let grid = SudokuText {
+            sudoku: &sudoku,
+            format: SudokuFormat::Grid,
+        }
+        .to_string();
+        assert_eq!(parse_sudoku_list::<9>(&grid), vec![(sudoku, String::new())]);
+        assert_eq!(parse_sudoku_list::<9>("12345"), Vec::new());
+    }
+}
@@ -0,0 +1,255 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::solver::fpuzzles::number;
+use crate::solver::links::ImportError;
+use crate::solver::sudoku::{Cell, DotKind, Puzzle, Variant};
+
+// SudokuPad shortens the keys in its links, so both the full and the short names are accepted.
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SclCell {
+    #[serde(alias = "v")]
+    value: Value,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SclCage {
+    #[serde(alias = "ce")]
+    cells: Vec<[i64; 2]>,
+    #[serde(alias = "v")]
+    value: Value,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SclOverlay {
+    #[serde(alias = "c")]
+    center: [f64; 2],
+    #[serde(alias = "w")]
+    width: f64,
+    /// Written as `true`, or as `1` in the shortened form.
+    #[serde(alias = "r")]
+    rounded: Value,
+    #[serde(alias = "ba")]
+    background_color: String,
+}
+
+/// The parts of the SudokuPad format that can be recognized. Lines are only described by their
+/// color there, so they are not imported.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SclPuzzle {
+    #[serde(alias = "ce")]
+    cells: Vec<Vec<SclCell>>,
+    #[serde(alias = "re")]
+    regions: Vec<Vec<[i64; 2]>>,
+    #[serde(alias = "ca")]
+    cages: Vec<SclCage>,
+    #[serde(alias = "ov")]
+    overlays: Vec<SclOverlay>,
+}
+
+/// Quotes the keys that SudokuPad leaves bare in the shortened form, such as `{ce:[...]}`.
+fn quote_keys(json: &str) -> String {
+    let mut res = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut in_string = false;
+    // Whether a key can start here, that is after `{` or `,` outside of a string.
+    let mut at_key = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            res.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    res.push(escaped);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        if at_key && (c.is_ascii_alphabetic() || c == '_') {
+            let mut key = String::from(c);
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphanumeric() && c != '_' {
+                    break;
+                }
+                key.push(c);
+                chars.next();
+            }
+            let rest = chars.clone().find(|c| !c.is_whitespace());
+            if rest == Some(':') {
+                res.push_str(&format!("\"{}\"", key));
+            } else {
+                res.push_str(&key);
+            }
+            at_key = false;
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                at_key = false;
+            }
+            '{' | ',' => at_key = true,
+            c if c.is_whitespace() => {}
+            _ => at_key = false,
+        }
+        res.push(c);
+    }
+    return res;
+}
+
+fn grid_cell<const N: usize>([row, col]: [i64; 2]) -> Option<Cell> {
+    if 0 <= row && row < N as i64 && 0 <= col && col < N as i64 {
+        return Some((row as usize, col as usize));
+    }
+    return None;
+}
+
+/// Small round overlays centered on the edge between two cells.
+fn dot<const N: usize>(overlay: &SclOverlay) -> Option<Variant> {
+    let rounded = match &overlay.rounded {
+        Value::Bool(b) => *b,
+        value => number(value).unwrap_or(0) != 0,
+    };
+    if !rounded || overlay.width > 0.5 {
+        return None;
+    }
+    let kind = match overlay.background_color.to_uppercase().as_str() {
+        "#FFF" | "#FFFFFF" | "WHITE" => DotKind::White,
+        "#000" | "#000000" | "BLACK" => DotKind::Black,
+        _ => return None,
+    };
+    let [r, c] = overlay.center;
+    let (a, b) = if r.fract() == 0.5 && c.fract() == 0.0 {
+        ([r.floor(), c - 1.0], [r.floor(), c])
+    } else if r.fract() == 0.0 && c.fract() == 0.5 {
+        ([r - 1.0, c.floor()], [r, c.floor()])
+    } else {
+        return None;
+    };
+    let a = grid_cell::<N>([a[0] as i64, a[1] as i64])?;
+    let b = grid_cell::<N>([b[0] as i64, b[1] as i64])?;
+    return Some(Variant::Dot {
+        kind,
+        cells: (a, b),
+    });
+}
+
+pub fn from_json<const N: usize>(json: &str) -> Result<Puzzle<N>, ImportError> {
+    let puzzle: SclPuzzle =
+        serde_json::from_str(&quote_keys(json)).map_err(|e| ImportError::Json(e.to_string()))?;
+    if puzzle.cells.len() != N {
+        return Err(ImportError::Size(puzzle.cells.len()));
+    }
+    let mut res = Puzzle::empty();
+    for (i, row) in puzzle.cells.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate().take(N) {
+            res.sudoku[i][j] = number(&cell.value).filter(|&v| 1 <= v && v <= N as u32);
+        }
+    }
+    if puzzle.regions.len() == N {
+        for (k, region) in puzzle.regions.iter().enumerate() {
+            for (r, c) in region.iter().filter_map(|&c| grid_cell::<N>(c)) {
+                res.regions[r][c] = k;
+            }
+        }
+    }
+    for cage in &puzzle.cages {
+        let mut cells = cage
+            .cells
+            .iter()
+            .filter_map(|&c| grid_cell::<N>(c))
+            .collect::<Vec<_>>();
+        // Cages are also used for metadata such as the title, which covers the whole grid.
+        let metadata = matches!(&cage.value, Value::String(s) if s.contains(':'));
+        if !cells.is_empty() && cells.len() < N * N && !metadata {
+            cells.sort();
+            let total = number(&cage.value);
+            res.variants.push(Variant::Killer { cells, total });
+        }
+    }
+    res.variants
+        .extend(puzzle.overlays.iter().filter_map(dot::<N>));
+    return Ok(res);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_givens_cages_and_dots() {
+        let mut cells = vec![vec!["{}"; 4]; 4];
+        cells[0][1] = "{\"value\":3}";
+        cells[3][3] = "{\"value\":\"4\"}";
+        let cells = cells
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(
+            "{{\"cells\":[{}],\"cages\":[{{\"cells\":[[0,0],[1,0]],\"value\":5}}],\
+             \"overlays\":[{{\"center\":[0.5,2],\"width\":0.3,\"rounded\":true,\
+             \"backgroundColor\":\"#000\"}}]}}",
+            cells
+        );
+        let puzzle = from_json::<4>(&json).unwrap();
+        assert_eq!(puzzle.sudoku[0][1], Some(3));
+        assert_eq!(puzzle.sudoku[3][3], Some(4));
+        assert_eq!(
+            puzzle.variants,
+            vec![
+                Variant::Killer {
+                    cells: vec![(0, 0), (1, 0)],
+                    total: Some(5),
+                },
+                Variant::Dot {
+                    kind: DotKind::Black,
+                    cells: ((0, 1), (0, 2)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_shortened_keys() {
+        let json = "{ce:[[{},{v:2}],[{},{}]],re:[[[0,0],[0,1]],[[1,0],[1,1]]],\
+                    ca:[{ce:[[0,0],[1,0]],v:\"3\"},{ce:[[0,0]],v:\"title: a, b\"}],\
+                    ov:[{c:[1,1.5],w:0.3,h:0.3,r:1,ba:\"#FFFFFF\"}]}";
+        let puzzle = from_json::<2>(json).unwrap();
+        assert_eq!(puzzle.sudoku, [[None, Some(2)], [None, None]]);
+        assert_eq!(puzzle.regions, [[0, 0], [1, 1]]);
+        assert_eq!(
+            puzzle.variants,
+            vec![
+                Variant::Killer {
+                    cells: vec![(0, 0), (1, 0)],
+                    total: Some(3),
+                },
+                Variant::Dot {
+                    kind: DotKind::White,
+                    cells: ((0, 1), (1, 1)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert!(matches!(
+            from_json::<9>("{\"cells\":"),
+            Err(ImportError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_grid_size() {
+        let json = format!("{{\"cells\":[{}]}}", ["[]"; 4].join(","));
+        assert_eq!(from_json::<9>(&json), Err(ImportError::Size(4)));
+    }
+}
@@ -0,0 +1,1190 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::solver::{constraint::Constraint, domain::DomainSet, solver::Problem};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum XcspError {
+    Xml(String),
+    Unsupported(String),
+    Variable(String),
+    Value(String),
+}
+
+impl fmt::Display for XcspError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XcspError::Xml(err) => write!(f, "invalid XML: {}", err),
+            XcspError::Unsupported(what) => write!(f, "unsupported XCSP3 feature: {}", what),
+            XcspError::Variable(name) => write!(f, "unknown variable {}", name),
+            XcspError::Value(value) => write!(f, "invalid value {}", value),
+        }
+    }
+}
+
+/// An XML element. Only the text directly inside the element is kept, so mixed content is joined.
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        return self
+            .attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str());
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        return self.children.iter().find(|child| child.name == name);
+    }
+
+    fn replace(&mut self, from: &str, to: &str) {
+        self.text = self.text.replace(from, to);
+        for child in &mut self.children {
+            child.replace(from, to);
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    return text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
+
+/// A reader for the XML used by XCSP3: elements, attributes, text, comments and CDATA.
+struct XmlReader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        return &self.text[self.pos..];
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips up to and including the end marker.
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, XcspError> {
+        match self.rest().find(end) {
+            Some(i) => {
+                let skipped = &self.rest()[..i];
+                self.pos += i + end.len();
+                return Ok(skipped);
+            }
+            None => return Err(XcspError::Xml(format!("missing {}", end))),
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "/>=".contains(c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        return &rest[..len];
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), XcspError> {
+        if !self.rest().starts_with(s) {
+            return Err(XcspError::Xml(format!("expected {} at {}", s, self.pos)));
+        }
+        self.pos += s.len();
+        return Ok(());
+    }
+
+    fn document(&mut self) -> Result<Element, XcspError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return self.element();
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Element, XcspError> {
+        self.expect("<")?;
+        let mut element = Element {
+            name: self.name().to_owned(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+        };
+        if element.name.is_empty() {
+            return Err(XcspError::Xml(format!(
+                "missing element name at {}",
+                self.pos
+            )));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name().to_owned();
+            if key.is_empty() {
+                return Err(XcspError::Xml(format!("invalid attribute at {}", self.pos)));
+            }
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.rest().starts_with('\'') {
+                "'"
+            } else {
+                "\""
+            };
+            self.expect(quote)?;
+            let value = unescape(self.skip_past(quote)?);
+            element.attributes.push((key, value));
+        }
+        loop {
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                let name = self.name();
+                if name != element.name {
+                    return Err(XcspError::Xml(format!(
+                        "{} closed by {}",
+                        element.name, name
+                    )));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.pos += 9;
+                element.text.push_str(self.skip_past("]]>")?);
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else if self.rest().is_empty() {
+                return Err(XcspError::Xml(format!("{} is not closed", element.name)));
+            } else {
+                let len = self.rest().find('<').unwrap_or(self.rest().len());
+                element.text.push_str(&unescape(&self.rest()[..len]));
+                element.text.push(' ');
+                self.pos += len;
+            }
+        }
+    }
+}
+
+fn integer(token: &str) -> Result<i64, XcspError> {
+    return token
+        .trim()
+        .parse()
+        .map_err(|_| XcspError::Value(token.trim().to_owned()));
+}
+
+fn value(token: &str) -> Result<u32, XcspError> {
+    return integer(token)?
+        .try_into()
+        .ok()
+        .filter(|&value| value < 64)
+        .ok_or_else(|| XcspError::Value(token.trim().to_owned()));
+}
+
+/// Reads a list of values and intervals such as `1 3 5..8`.
+fn read_domain(text: &str) -> Result<DomainSet, XcspError> {
+    let mut domain = DomainSet::empty();
+    for token in text.split_whitespace() {
+        match token.split_once("..") {
+            Some((low, high)) => domain.add_all(DomainSet::range(value(low)?..value(high)? + 1)),
+            None => domain.add(value(token)?),
+        }
+    }
+    return Ok(domain);
+}
+
+fn write_domain(domain: DomainSet) -> String {
+    match (domain.get_any(), domain.max()) {
+        (Some(low), Some(high)) if low < high && domain.len() as u32 == high - low + 1 => {
+            return format!("{}..{}", low, high);
+        }
+        _ => {
+            let values = domain.map(|value| value.to_string()).collect::<Vec<_>>();
+            return values.join(" ");
+        }
+    }
+}
+
+/// A functional expression of an intension constraint.
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Int(i64),
+    Var(usize),
+    Call(String, Vec<Expr>),
+}
+
+const FUNCTIONS: [&str; 26] = [
+    "neg", "abs", "add", "sub", "mul", "div", "mod", "sqr", "pow", "min", "max", "dist", "lt",
+    "le", "ge", "gt", "ne", "eq", "not", "and", "or", "xor", "iff", "imp", "if", "in",
+];
+
+impl Expr {
+    /// Collects the variables in order of their first appearance, and all integers in order.
+    fn leaves(&self, vars: &mut Vec<usize>, ints: &mut Vec<i64>) {
+        match self {
+            Expr::Int(value) => ints.push(*value),
+            Expr::Var(var) => {
+                if !vars.contains(var) {
+                    vars.push(*var);
+                }
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    arg.leaves(vars, ints);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the expression with booleans as zero and one, or `None` if it is undefined or
+    /// overflows.
+    fn eval(&self, values: &[(usize, i64)]) -> Option<i64> {
+        let (name, args) = match self {
+            Expr::Int(value) => return Some(*value),
+            Expr::Var(var) => return values.iter().find(|(v, _)| v == var).map(|&(_, x)| x),
+            Expr::Call(name, args) => (name.as_str(), args),
+        };
+        if name == "in" && args.len() == 2 {
+            let x = args[0].eval(values)?;
+            let set = match &args[1] {
+                Expr::Call(name, set) if name == "set" => set,
+                _ => return None,
+            };
+            for element in set {
+                if element.eval(values)? == x {
+                    return Some(1);
+                }
+            }
+            return Some(0);
+        }
+        let x = args
+            .iter()
+            .map(|arg| arg.eval(values))
+            .collect::<Option<Vec<_>>>()?;
+        let bool = |b: bool| Some(b as i64);
+        let res = match (name, x.as_slice()) {
+            ("neg", &[a]) => a.checked_neg()?,
+            ("abs", &[a]) => a.checked_abs()?,
+            ("sqr", &[a]) => a.checked_mul(a)?,
+            ("not", &[a]) => (a == 0) as i64,
+            ("sub", &[a, b]) => a.checked_sub(b)?,
+            ("div", &[a, b]) => a.checked_div(b)?,
+            ("mod", &[a, b]) => a.checked_rem(b)?,
+            ("pow", &[a, b]) => a.checked_pow(b.try_into().ok()?)?,
+            ("dist", &[a, b]) => a.checked_sub(b)?.checked_abs()?,
+            ("lt", &[a, b]) => return bool(a < b),
+            ("le", &[a, b]) => return bool(a <= b),
+            ("ge", &[a, b]) => return bool(a >= b),
+            ("gt", &[a, b]) => return bool(a > b),
+            ("ne", &[a, b]) => return bool(a != b),
+            ("imp", &[a, b]) => return bool(a == 0 || b != 0),
+            ("if", &[c, a, b]) => return Some(if c != 0 { a } else { b }),
+            ("add", _) => x.iter().try_fold(0i64, |s, &a| s.checked_add(a))?,
+            ("mul", _) => x.iter().try_fold(1i64, |p, &a| p.checked_mul(a))?,
+            ("min", _) => *x.iter().min()?,
+            ("max", _) => *x.iter().max()?,
+            ("eq", _) => return bool(x.windows(2).all(|w| w[0] == w[1])),
+            ("and", _) => return bool(x.iter().all(|&a| a != 0)),
+            ("or", _) => return bool(x.iter().any(|&a| a != 0)),
+            ("xor", _) => return bool(!x.iter().filter(|&&a| a != 0).count().is_multiple_of(2)),
+            ("iff", _) => return bool(x.windows(2).all(|w| (w[0] != 0) == (w[1] != 0))),
+            _ => return None,
+        };
+        return Some(res);
+    }
+}
+
+fn call(name: &str, args: Vec<String>) -> String {
+    return format!("{}({})", name, args.join(","));
+}
+
+/// Joins the arguments with an n-ary function, which needs at least two of them.
+fn nary(name: &str, identity: &str, mut args: Vec<String>) -> String {
+    match args.len() {
+        0 => return identity.to_owned(),
+        1 => return args.remove(0),
+        _ => return call(name, args),
+    }
+}
+
+/// Writes the constraints that have no counterpart in XCSP3 as intension constraints.
+fn intension(constraint: &Constraint, name: &dyn Fn(usize) -> String) -> Option<String> {
+    let eq = |var: usize, value: u32| call("eq", vec![name(var), value.to_string()]);
+    match constraint {
+        Constraint::ForbiddenDifference(a, b, forbidden) => {
+            let set = call("set", forbidden.map(|value| value.to_string()).collect());
+            let dist = call("dist", vec![name(*a), name(*b)]);
+            return Some(call("not", vec![call("in", vec![dist, set])]));
+        }
+        Constraint::Consecutive(vars) => {
+            let names = vars.iter().map(|&var| name(var)).collect::<Vec<_>>();
+            let range = call(
+                "sub",
+                vec![nary("max", "0", names.clone()), nary("min", "0", names)],
+            );
+            return Some(call("lt", vec![range, vars.len().to_string()]));
+        }
+        Constraint::Sandwich(vars, (low, high), total) => {
+            // Equal crusts have to appear twice, and then only one pair of positions matches.
+            let count = if low == high { "2" } else { "1" };
+            let low_count = nary("add", "0", vars.iter().map(|&v| eq(v, *low)).collect());
+            let high_count = nary("add", "0", vars.iter().map(|&v| eq(v, *high)).collect());
+            let mut between = Vec::new();
+            for (i, &a) in vars.iter().enumerate() {
+                for (j, &b) in vars.iter().enumerate().skip(i + 2) {
+                    let crusts = call(
+                        "or",
+                        vec![
+                            call("and", vec![eq(a, *low), eq(b, *high)]),
+                            call("and", vec![eq(a, *high), eq(b, *low)]),
+                        ],
+                    );
+                    // The values are one less than the digits.
+                    let mut digits = vars[i + 1..j].iter().map(|&v| name(v)).collect::<Vec<_>>();
+                    digits.push((j - i - 1).to_string());
+                    between.push(call("mul", vec![crusts, call("add", digits)]));
+                }
+            }
+            return Some(call(
+                "and",
+                vec![
+                    call("eq", vec![low_count, count.to_owned()]),
+                    call("eq", vec![high_count, count.to_owned()]),
+                    call("eq", vec![nary("add", "0", between), total.to_string()]),
+                ],
+            ));
+        }
+        Constraint::Skyscraper(vars, count) => {
+            let visible = (0..vars.len()).map(|i| {
+                let lower = vars[..i]
+                    .iter()
+                    .map(|&v| call("lt", vec![name(v), name(vars[i])]));
+                return nary("and", "1", lower.collect());
+            });
+            let sum = nary("add", "0", visible.collect());
+            let mut terms = Vec::new();
+            for (i, &a) in vars.iter().enumerate() {
+                for &b in &vars[i + 1..] {
+                    terms.push(call("ne", vec![name(a), name(b)]));
+                }
+            }
+            terms.push(call("eq", vec![sum, count.to_string()]));
+            return Some(nary("and", "1", terms));
+        }
+        _ => return None,
+    }
+}
+
+fn list(vars: &[usize]) -> String {
+    let names = vars
+        .iter()
+        .map(|var| format!("x{}", var))
+        .collect::<Vec<_>>();
+    return names.join(" ");
+}
+
+fn write_constraint(res: &mut String, constraint: &Constraint) {
+    let name = |var: usize| format!("x{}", var);
+    if let Some(expr) = intension(constraint, &name) {
+        writeln!(res, "    <intension> {} </intension>", expr).unwrap();
+        return;
+    }
+    match constraint {
+        Constraint::AllDifferent(vars) => {
+            writeln!(res, "    <allDifferent> {} </allDifferent>", list(vars)).unwrap();
+        }
+        Constraint::Sum(terms, total) => {
+            let vars = terms.iter().map(|&(var, _)| var).collect::<Vec<_>>();
+            let coeffs = terms
+                .iter()
+                .map(|&(_, w)| w.to_string())
+                .collect::<Vec<_>>();
+            res.push_str("    <sum>\n");
+            writeln!(res, "      <list> {} </list>", list(&vars)).unwrap();
+            writeln!(res, "      <coeffs> {} </coeffs>", coeffs.join(" ")).unwrap();
+            writeln!(res, "      <condition> (eq,{}) </condition>", total).unwrap();
+            res.push_str("    </sum>\n");
+        }
+        Constraint::Increasing(vars) => {
+            res.push_str("    <ordered>\n");
+            writeln!(res, "      <list> {} </list>", list(vars)).unwrap();
+            res.push_str("      <operator> lt </operator>\n");
+            res.push_str("    </ordered>\n");
+        }
+        Constraint::Table(a, b, allowed) => {
+            let mut tuples = String::new();
+            for (j, values) in allowed.iter().enumerate() {
+                for k in *values {
+                    write!(tuples, "({},{})", j, k).unwrap();
+                }
+            }
+            res.push_str("    <extension>\n");
+            writeln!(res, "      <list> {} </list>", list(&[*a, *b])).unwrap();
+            writeln!(res, "      <supports> {} </supports>", tuples).unwrap();
+            res.push_str("    </extension>\n");
+        }
+        Constraint::GlobalCardinality(vars, bounds) => {
+            let values = (0..bounds.len()).map(|j| j.to_string()).collect::<Vec<_>>();
+            let occurs = bounds
+                .iter()
+                .map(|&(low, high)| match low == high {
+                    true => low.to_string(),
+                    false => format!("{}..{}", low, high),
+                })
+                .collect::<Vec<_>>();
+            res.push_str("    <cardinality>\n");
+            writeln!(res, "      <list> {} </list>", list(vars)).unwrap();
+            writeln!(
+                res,
+                "      <values closed=\"true\"> {} </values>",
+                values.join(" ")
+            )
+            .unwrap();
+            writeln!(res, "      <occurs> {} </occurs>", occurs.join(" ")).unwrap();
+            res.push_str("    </cardinality>\n");
+        }
+        _ => {}
+    }
+}
+
+/// Builds a problem from the elements of an XCSP3 instance.
+struct Reader {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    arrays: HashMap<String, Vec<usize>>,
+    domains: Vec<DomainSet>,
+    constraints: Vec<Constraint>,
+}
+
+/// Splits a variable reference such as `x[2][]` into the array name and the index ranges, where
+/// an empty range stands for all indices.
+fn split_reference(token: &str) -> Option<(&str, Vec<&str>)> {
+    let (base, mut rest) = token.split_once('[')?;
+    let mut parts = Vec::new();
+    loop {
+        let (part, after) = rest.split_once(']')?;
+        parts.push(part);
+        match after.strip_prefix('[') {
+            Some(next) => rest = next,
+            None if after.is_empty() => return Some((base, parts)),
+            None => return None,
+        }
+    }
+}
+
+fn index_range(part: &str, size: usize) -> Result<(usize, usize), XcspError> {
+    let index = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| XcspError::Value(s.to_owned()))
+    };
+    if part.trim().is_empty() {
+        return Ok((0, size));
+    }
+    match part.split_once("..") {
+        Some((low, high)) => return Ok((index(low)?, index(high)? + 1)),
+        None => return Ok((index(part)?, index(part)? + 1)),
+    }
+}
+
+fn sizes(size: &str) -> Result<Vec<usize>, XcspError> {
+    return size
+        .split(['[', ']'])
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim().parse().map_err(|_| XcspError::Value(s.to_owned())))
+        .collect();
+}
+
+/// All index tuples within the ranges, in row-major order.
+fn indices(ranges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut res = vec![Vec::new()];
+    for &(low, high) in ranges {
+        res = res
+            .into_iter()
+            .flat_map(|prefix| {
+                (low..high).map(move |i| {
+                    let mut next = prefix.clone();
+                    next.push(i);
+                    return next;
+                })
+            })
+            .collect();
+    }
+    return res;
+}
+
+fn cell_name(base: &str, index: &[usize]) -> String {
+    let mut name = base.to_owned();
+    for i in index {
+        write!(name, "[{}]", i).unwrap();
+    }
+    return name;
+}
+
+impl Reader {
+    fn add_variable(&mut self, name: String, domain: DomainSet) {
+        self.index.insert(name.clone(), self.names.len());
+        self.names.push(name);
+        self.domains.push(domain);
+    }
+
+    fn variables(&mut self, element: &Element) -> Result<(), XcspError> {
+        for var in &element.children {
+            if matches!(var.attribute("type"), Some(kind) if kind != "integer") {
+                return Err(XcspError::Unsupported(format!("{} variables", var.name)));
+            }
+            let id = var.attribute("id").unwrap_or_default().to_owned();
+            match var.name.as_str() {
+                "var" => {
+                    let domain = match var.attribute("as") {
+                        Some(other) => *self
+                            .index
+                            .get(other)
+                            .map(|&i| &self.domains[i])
+                            .ok_or_else(|| XcspError::Variable(other.to_owned()))?,
+                        None => read_domain(&var.text)?,
+                    };
+                    self.add_variable(id, domain);
+                }
+                "array" => {
+                    let sizes = sizes(var.attribute("size").unwrap_or_default())?;
+                    let cells = indices(&sizes.iter().map(|&s| (0, s)).collect::<Vec<_>>());
+                    let mut domains = vec![None; cells.len()];
+                    if var.children.is_empty() {
+                        domains = vec![Some(read_domain(&var.text)?); cells.len()];
+                    }
+                    for part in var.children.iter().filter(|c| c.name == "domain") {
+                        let domain = read_domain(&part.text)?;
+                        for target in part.attribute("for").unwrap_or_default().split_whitespace() {
+                            if target == "others" {
+                                for d in domains.iter_mut().filter(|d| d.is_none()) {
+                                    *d = Some(domain);
+                                }
+                                continue;
+                            }
+                            let (_, parts) = split_reference(target)
+                                .ok_or_else(|| XcspError::Variable(target.to_owned()))?;
+                            let ranges = parts
+                                .iter()
+                                .zip(&sizes)
+                                .map(|(part, &size)| index_range(part, size))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            for index in indices(&ranges) {
+                                let k = index.iter().zip(&sizes).fold(0, |k, (i, s)| k * s + i);
+                                if let Some(d) = domains.get_mut(k) {
+                                    *d = Some(domain);
+                                }
+                            }
+                        }
+                    }
+                    // Cells without a domain are not part of the array.
+                    for (index, domain) in cells.iter().zip(domains) {
+                        if let Some(domain) = domain {
+                            self.add_variable(cell_name(&id, index), domain);
+                        }
+                    }
+                    self.arrays.insert(id, sizes);
+                }
+                other => return Err(XcspError::Unsupported(other.to_owned())),
+            }
+        }
+        return Ok(());
+    }
+
+    /// Expands a variable, or all cells of an array covered by a reference such as `x[1..2][]`.
+    fn expand(&self, token: &str) -> Result<Vec<usize>, XcspError> {
+        if let Some(&var) = self.index.get(token) {
+            return Ok(vec![var]);
+        }
+        let unknown = || XcspError::Variable(token.to_owned());
+        let (base, parts) = split_reference(token).ok_or_else(unknown)?;
+        let sizes = self.arrays.get(base).ok_or_else(unknown)?;
+        if parts.len() != sizes.len() {
+            return Err(unknown());
+        }
+        let ranges = parts
+            .iter()
+            .zip(sizes)
+            .map(|(part, &size)| index_range(part, size))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Cells without a domain are skipped.
+        return Ok(indices(&ranges)
+            .iter()
+            .filter_map(|index| self.index.get(&cell_name(base, index)).copied())
+            .collect());
+    }
+
+    fn vars(&self, text: &str) -> Result<Vec<usize>, XcspError> {
+        let mut res = Vec::new();
+        for token in text.split_whitespace() {
+            res.extend(self.expand(token)?);
+        }
+        return Ok(res);
+    }
+
+    /// Reads the rows of a matrix, given as tuples or as a reference to a two-dimensional array.
+    fn matrix(&self, text: &str) -> Result<Vec<Vec<usize>>, XcspError> {
+        if text.contains('(') {
+            return text
+                .split(')')
+                .filter(|row| !row.trim().is_empty())
+                .map(|row| self.vars(&row.replace(['(', ','], " ")))
+                .collect();
+        }
+        let token = text.trim();
+        let unknown = || XcspError::Variable(token.to_owned());
+        let (base, parts) = split_reference(token).ok_or_else(unknown)?;
+        let sizes = self.arrays.get(base).ok_or_else(unknown)?;
+        if parts.len() != 2 || sizes.len() != 2 {
+            return Err(XcspError::Unsupported(format!("matrix {}", token)));
+        }
+        let (low, high) = index_range(parts[0], sizes[0])?;
+        return (low..high)
+            .map(|i| self.expand(&format!("{}[{}][{}]", base, i, parts[1])))
+            .collect();
+    }
+
+    fn parse(&self, text: &mut &str) -> Result<Expr, XcspError> {
+        let malformed = |s: &str| XcspError::Value(format!("expression {}", s));
+        *text = text.trim_start();
+        let len = text
+            .find(|c: char| "(),".contains(c) || c.is_whitespace())
+            .unwrap_or(text.len());
+        let token = &text[..len];
+        *text = text[len..].trim_start();
+        if token.is_empty() {
+            return Err(malformed(text));
+        }
+        if let Some(rest) = text.strip_prefix('(') {
+            if token != "set" && !FUNCTIONS.contains(&token) {
+                return Err(XcspError::Unsupported(format!("function {}", token)));
+            }
+            *text = rest.trim_start();
+            let mut args = Vec::new();
+            while !text.starts_with(')') {
+                args.push(self.parse(text)?);
+                *text = text.trim_start();
+                if let Some(rest) = text.strip_prefix(',') {
+                    *text = rest;
+                } else if !text.starts_with(')') {
+                    return Err(malformed(text));
+                }
+            }
+            *text = &text[1..];
+            return Ok(Expr::Call(token.to_owned(), args));
+        }
+        if let Ok(value) = token.parse() {
+            return Ok(Expr::Int(value));
+        }
+        match self.index.get(token) {
+            Some(&var) => return Ok(Expr::Var(var)),
+            None => return Err(XcspError::Variable(token.to_owned())),
+        }
+    }
+
+    fn expression(&self, mut text: &str) -> Result<Expr, XcspError> {
+        let expr = self.parse(&mut text)?;
+        if !text.trim().is_empty() {
+            return Err(XcspError::Value(format!("expression {}", text.trim())));
+        }
+        return Ok(expr);
+    }
+
+    /// Recognizes the constraints written as intension constraints by `to_xcsp`. Other
+    /// expressions are supported on up to two variables, by listing the values they allow.
+    fn intension(&mut self, text: &str) -> Result<(), XcspError> {
+        let expr = self.expression(text)?;
+        let (mut vars, mut ints) = (Vec::new(), Vec::new());
+        expr.leaves(&mut vars, &mut ints);
+        let n = vars.len();
+        let last = ints.last().copied().unwrap_or(0);
+        let crust = |i: usize| {
+            let value = ints.get(i).and_then(|&v| u32::try_from(v).ok());
+            return value.filter(|&v| v < 64).unwrap_or(0);
+        };
+        let mut candidates = vec![
+            Constraint::Consecutive(vars.clone()),
+            Constraint::Skyscraper(vars.clone(), last.max(0) as usize),
+            Constraint::Sandwich(vars.clone(), (crust(0), crust(n + 1)), last),
+        ];
+        if n == 2 {
+            let mut forbidden = DomainSet::empty();
+            for &v in ints.iter().filter(|&&v| (0..64).contains(&v)) {
+                forbidden.add(v as u32);
+            }
+            candidates.push(Constraint::ForbiddenDifference(vars[0], vars[1], forbidden));
+        }
+        let name = |var: usize| self.names[var].clone();
+        for candidate in candidates {
+            if let Some(written) = intension(&candidate, &name) {
+                if self.expression(&written)? == expr {
+                    self.constraints.push(candidate);
+                    return Ok(());
+                }
+            }
+        }
+        let holds = |values: &[(usize, i64)]| expr.eval(values).unwrap_or(0) != 0;
+        match vars.as_slice() {
+            [] => {
+                if !holds(&[]) {
+                    self.constraints.push(Constraint::Sum(Vec::new(), 1));
+                }
+            }
+            &[a] => {
+                for j in self.domains[a] {
+                    if !holds(&[(a, j as i64)]) {
+                        self.domains[a].remove(j);
+                    }
+                }
+            }
+            &[a, b] => {
+                let size = self.domains[a].max().map_or(0, |max| max as usize + 1);
+                let mut allowed = vec![DomainSet::empty(); size];
+                for j in self.domains[a] {
+                    for k in self.domains[b] {
+                        if holds(&[(a, j as i64), (b, k as i64)]) {
+                            allowed[j as usize].add(k);
+                        }
+                    }
+                }
+                while allowed.last() == Some(&DomainSet::empty()) {
+                    allowed.pop();
+                }
+                self.constraints.push(Constraint::Table(a, b, allowed));
+            }
+            _ => {
+                return Err(XcspError::Unsupported(format!(
+                    "intension on {} variables",
+                    n
+                )))
+            }
+        }
+        return Ok(());
+    }
+
+    fn extension(&mut self, element: &Element) -> Result<(), XcspError> {
+        let vars = self.vars(list_text(element))?;
+        let (tuples, supports) = match (element.child("supports"), element.child("conflicts")) {
+            (Some(tuples), _) => (&tuples.text, true),
+            (None, Some(tuples)) => (&tuples.text, false),
+            (None, None) => return Err(XcspError::Unsupported("extension without tuples".into())),
+        };
+        match *vars.as_slice() {
+            [a] => {
+                let mut values = DomainSet::empty();
+                for token in tuples.split_whitespace() {
+                    values.add_all(read_domain(token).unwrap_or(DomainSet::empty()));
+                }
+                match supports {
+                    true => self.domains[a].retain_all(values),
+                    false => self.domains[a].remove_all(values),
+                }
+            }
+            [a, b] => {
+                let mut allowed = match supports {
+                    true => Vec::new(),
+                    false => {
+                        let size = self.domains[a].max().map_or(0, |max| max as usize + 1);
+                        vec![self.domains[b]; size]
+                    }
+                };
+                // Values outside of the domains can not be part of a solution either way.
+                let values = |token: &str, domain: DomainSet| match token.trim() {
+                    "*" => Some(domain),
+                    token => value(token).ok().map(DomainSet::singleton),
+                };
+                for tuple in tuples.split(')') {
+                    let tuple = tuple.trim().trim_start_matches('(');
+                    if tuple.is_empty() {
+                        continue;
+                    }
+                    let (j, k) = tuple
+                        .split_once(',')
+                        .ok_or_else(|| XcspError::Value(format!("tuple ({})", tuple)))?;
+                    if let (Some(js), Some(ks)) =
+                        (values(j, self.domains[a]), values(k, self.domains[b]))
+                    {
+                        for j in js {
+                            if supports && allowed.len() <= j as usize {
+                                allowed.resize(j as usize + 1, DomainSet::empty());
+                            }
+                            if let Some(set) = allowed.get_mut(j as usize) {
+                                match supports {
+                                    true => set.add_all(ks),
+                                    false => set.remove_all(ks),
+                                }
+                            }
+                        }
+                    }
+                }
+                while allowed.last() == Some(&DomainSet::empty()) {
+                    allowed.pop();
+                }
+                self.constraints.push(Constraint::Table(a, b, allowed));
+            }
+            _ => {
+                return Err(XcspError::Unsupported(format!(
+                    "extension on {} variables",
+                    vars.len()
+                )))
+            }
+        }
+        return Ok(());
+    }
+
+    fn sum(&mut self, element: &Element) -> Result<(), XcspError> {
+        let vars = self.vars(list_text(element))?;
+        let coeffs = match element.child("coeffs") {
+            Some(coeffs) => integers(&coeffs.text)?,
+            None => vec![1; vars.len()],
+        };
+        if coeffs.len() != vars.len() {
+            return Err(XcspError::Value(format!("{} coefficients", coeffs.len())));
+        }
+        let condition = element.child("condition").map_or("", |c| c.text.trim());
+        let condition = condition.trim_start_matches('(').trim_end_matches(')');
+        let total = match condition.split_once(',') {
+            Some((op, total)) if op.trim() == "eq" => integer(total)?,
+            _ => {
+                return Err(XcspError::Unsupported(format!(
+                    "sum condition ({})",
+                    condition
+                )))
+            }
+        };
+        let terms = vars.into_iter().zip(coeffs).collect();
+        self.constraints.push(Constraint::Sum(terms, total));
+        return Ok(());
+    }
+
+    fn cardinality(&mut self, element: &Element) -> Result<(), XcspError> {
+        let vars = self.vars(list_text(element))?;
+        let (values, occurs) = match (element.child("values"), element.child("occurs")) {
+            (Some(values), Some(occurs)) => (values, occurs),
+            _ => return Err(XcspError::Unsupported("cardinality without values".into())),
+        };
+        let closed = values.attribute("closed") == Some("true");
+        let values = integers(&values.text)?
+            .into_iter()
+            .map(|v| value(&v.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| XcspError::Unsupported(format!("occurrences {}", s)))
+        };
+        let occurs = occurs
+            .text
+            .split_whitespace()
+            .map(|token| match token.split_once("..") {
+                Some((low, high)) => Ok((count(low)?, count(high)?)),
+                None => Ok((count(token)?, count(token)?)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if occurs.len() != values.len() {
+            return Err(XcspError::Value(format!("{} occurrences", occurs.len())));
+        }
+        let mut top = values.iter().max().map_or(0, |&v| v as usize + 1);
+        if !closed {
+            for &var in &vars {
+                top = top.max(self.domains[var].max().map_or(0, |max| max as usize + 1));
+            }
+        }
+        let open = if closed { (0, 0) } else { (0, vars.len()) };
+        let mut bounds = vec![open; top];
+        for (&value, &bound) in values.iter().zip(&occurs) {
+            bounds[value as usize] = bound;
+        }
+        self.constraints
+            .push(Constraint::GlobalCardinality(vars, bounds));
+        return Ok(());
+    }
+
+    /// Instantiates a template constraint with each of its argument lists, where `%i` stands for
+    /// the i-th argument and `%...` for all of them.
+    fn group(&mut self, element: &Element) -> Result<(), XcspError> {
+        let template = element
+            .children
+            .iter()
+            .find(|child| child.name != "args")
+            .ok_or_else(|| XcspError::Unsupported("group without constraint".into()))?;
+        for args in element.children.iter().filter(|child| child.name == "args") {
+            let mut tokens = Vec::new();
+            for token in args.text.split_whitespace() {
+                match self.expand(token) {
+                    Ok(vars) => tokens.extend(vars.iter().map(|&var| self.names[var].clone())),
+                    Err(_) => tokens.push(token.to_owned()),
+                }
+            }
+            let mut constraint = template.clone();
+            constraint.replace("%...", &tokens.join(" "));
+            for (i, token) in tokens.iter().enumerate().rev() {
+                constraint.replace(&format!("%{}", i), token);
+            }
+            self.constraint(&constraint)?;
+        }
+        return Ok(());
+    }
+
+    fn constraint(&mut self, element: &Element) -> Result<(), XcspError> {
+        match element.name.as_str() {
+            "allDifferent" => {
+                let lists = element.children.iter().filter(|c| c.name == "list");
+                if element.child("except").is_some() || lists.count() > 1 {
+                    return Err(XcspError::Unsupported("allDifferent with lists".into()));
+                }
+                match element.child("matrix") {
+                    Some(matrix) => {
+                        let rows = self.matrix(&matrix.text)?;
+                        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+                        for j in 0..width {
+                            let column = rows.iter().filter_map(|row| row.get(j).copied());
+                            self.constraints
+                                .push(Constraint::AllDifferent(column.collect()));
+                        }
+                        for row in rows {
+                            self.constraints.push(Constraint::AllDifferent(row));
+                        }
+                    }
+                    None => {
+                        let vars = self.vars(list_text(element))?;
+                        self.constraints.push(Constraint::AllDifferent(vars));
+                    }
+                }
+            }
+            "sum" => self.sum(element)?,
+            "ordered" => {
+                let mut vars = self.vars(list_text(element))?;
+                let operator = element.child("operator").map_or("", |op| op.text.trim());
+                if element.child("lengths").is_some() || !["lt", "gt"].contains(&operator) {
+                    return Err(XcspError::Unsupported(format!("ordered {}", operator)));
+                }
+                if operator == "gt" {
+                    vars.reverse();
+                }
+                self.constraints.push(Constraint::Increasing(vars));
+            }
+            "intension" => {
+                let text = element.child("function").unwrap_or(element).text.clone();
+                self.intension(&text)?;
+            }
+            "extension" => self.extension(element)?,
+            "cardinality" => self.cardinality(element)?,
+            "instantiation" => {
+                let vars = self.vars(list_text(element))?;
+                let values = element.child("values").map_or("", |v| v.text.as_str());
+                let values = values.split_whitespace().map(value);
+                for (var, value) in vars.into_iter().zip(values) {
+                    self.domains[var].retain_all(DomainSet::singleton(value?));
+                }
+            }
+            "group" => self.group(element)?,
+            "block" => {
+                for child in &element.children {
+                    self.constraint(child)?;
+                }
+            }
+            "annotations" => {}
+            other => return Err(XcspError::Unsupported(other.to_owned())),
+        }
+        return Ok(());
+    }
+}
+
+/// The list of a constraint, which can also be given without the `list` element.
+fn list_text(element: &Element) -> &str {
+    return element
+        .child("list")
+        .map_or(&element.text, |list| &list.text);
+}
+
+/// Reads integers, where `vxk` stands for the value `v` repeated `k` times.
+fn integers(text: &str) -> Result<Vec<i64>, XcspError> {
+    let mut res = Vec::new();
+    for token in text.split_whitespace() {
+        match token.split_once('x') {
+            Some((value, times)) => {
+                let times = integer(times)?.max(0) as usize;
+                let value = integer(value)?;
+                res.extend((0..times).map(|_| value));
+            }
+            None => res.push(integer(token)?),
+        }
+    }
+    return Ok(res);
+}
+
+impl Problem {
+    /// Writes the problem as an XCSP3 instance with one variable `x<i>` for every variable of the
+    /// problem. Constraints without a counterpart in XCSP3 are written as intension constraints.
+    pub fn to_xcsp(&self) -> String {
+        let mut res = "<instance format=\"XCSP3\" type=\"CSP\">\n  <variables>\n".to_owned();
+        for (var, &domain) in self.domains().iter().enumerate() {
+            writeln!(
+                res,
+                "    <var id=\"x{}\"> {} </var>",
+                var,
+                write_domain(domain)
+            )
+            .unwrap();
+        }
+        res.push_str("  </variables>\n  <constraints>\n");
+        for constraint in self.constraints() {
+            write_constraint(&mut res, constraint);
+        }
+        res.push_str("  </constraints>\n</instance>\n");
+        return res;
+    }
+
+    /// Reads an XCSP3 instance, together with the names of its variables. The values of the
+    /// variables have to be between 0 and 63.
+    pub fn from_xcsp(text: &str) -> Result<(Problem, Vec<String>), XcspError> {
+        let root = XmlReader { text, pos: 0 }.document()?;
+        if root.name != "instance" {
+            return Err(XcspError::Xml(format!("unexpected element {}", root.name)));
+        }
+        if let Some(kind) = root.attribute("type").filter(|&kind| kind != "CSP") {
+            return Err(XcspError::Unsupported(kind.to_owned()));
+        }
+        let mut reader = Reader {
+            names: Vec::new(),
+            index: HashMap::new(),
+            arrays: HashMap::new(),
+            domains: Vec::new(),
+            constraints: Vec::new(),
+        };
+        for child in &root.children {
+            match child.name.as_str() {
+                "variables" => reader.variables(child)?,
+                "constraints" => {
+                    for constraint in &child.children {
+                        reader.constraint(constraint)?;
+                    }
+                }
+                "annotations" => {}
+                other => return Err(XcspError::Unsupported(other.to_owned())),
+            }
+        }
+        let mut problem = Problem::with_capacity(reader.domains.len(), reader.constraints.len());
+        for domain in reader.domains {
+            problem.add_variable(domain);
+        }
+        for constraint in reader.constraints {
+            problem.add(constraint);
+        }
+        return Ok((problem, reader.names));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(variables: &str, constraints: &str) -> String {
+        return format!(
+            "<instance format=\"XCSP3\" type=\"CSP\"><variables>{}</variables>\
+             <constraints>{}</constraints></instance>",
+            variables, constraints
+        );
+    }
+
+    #[test]
+    fn round_trips_every_constraint() {
+        let mut problem = Problem::empty();
+        for size in [4, 4, 3, 4, 4] {
+            problem.add_variable(DomainSet::range(0..size));
+        }
+        let vars = vec![0, 1, 2, 3];
+        for constraint in [
+            Constraint::AllDifferent(vars.clone()),
+            Constraint::Sum(vec![(0, 1), (1, 2), (4, -1)], 3),
+            Constraint::ForbiddenDifference(0, 1, DomainSet::singleton(1)),
+            Constraint::Consecutive(vars.clone()),
+            Constraint::Increasing(vec![0, 2, 4]),
+            Constraint::Table(3, 4, vec![DomainSet::range(1..3), DomainSet::singleton(0)]),
+            Constraint::Sandwich(vars.clone(), (0, 3), 3),
+            Constraint::Skyscraper(vars.clone(), 2),
+            Constraint::GlobalCardinality(vars.clone(), vec![(1, 2), (0, 1), (1, 1)]),
+        ] {
+            problem.add(constraint);
+        }
+        let (read, names) = Problem::from_xcsp(&problem.to_xcsp()).unwrap();
+        assert_eq!(names, vec!["x0", "x1", "x2", "x3", "x4"]);
+        assert_eq!(read.domains(), problem.domains());
+        assert_eq!(read.constraints(), problem.constraints());
+    }
+
+    #[test]
+    fn reads_expressions_on_two_variables() {
+        let text = instance(
+            "<var id=\"a\"> 0..3 </var><var id=\"b\"> 0..3 </var>",
+            "<intension> eq(add(a,mul(2,b)),3) </intension>",
+        );
+        let (problem, _) = Problem::from_xcsp(&text).unwrap();
+        let allowed = vec![
+            DomainSet::empty(),
+            DomainSet::singleton(1),
+            DomainSet::empty(),
+            DomainSet::singleton(0),
+        ];
+        assert_eq!(problem.constraints(), &[Constraint::Table(0, 1, allowed)]);
+    }
+
+    #[test]
+    fn rejects_overflowing_expressions() {
+        let text = instance(
+            "<var id=\"a\"> 0..3 </var>",
+            "<intension> ne(mul(a,9223372036854775807,2),5) </intension>",
+        );
+        let (problem, _) = Problem::from_xcsp(&text).unwrap();
+        assert_eq!(problem.domains(), &[DomainSet::singleton(0)]);
+    }
+
+    #[test]
+    fn rejects_invalid_instances() {
+        assert!(matches!(
+            Problem::from_xcsp("<instance"),
+            Err(XcspError::Xml(_))
+        ));
+        let cop = "<instance format=\"XCSP3\" type=\"COP\"></instance>";
+        assert_eq!(
+            Problem::from_xcsp(cop).err(),
+            Some(XcspError::Unsupported("COP".into()))
+        );
+        let text = instance(
+            "<var id=\"a\"> 0..3 </var>",
+            "<allDifferent> a b </allDifferent>",
+        );
+        assert_eq!(
+            Problem::from_xcsp(&text).err(),
+            Some(XcspError::Variable("b".into()))
+        );
+    }
+}
@@ -1,11 +1,14 @@
 use serde::{de::DeserializeOwned, Serialize};
 use yew_agent::{Agent, AgentLink, HandlerId, Public};
 
+use crate::solver::multi::{MultiDomains, MultiLayout, MultiSudoku};
 use crate::solver::sudoku::*;
 
 pub struct SolvingWorker<const N: usize>
 where
     Sudoku<N>: Serialize + DeserializeOwned,
+    SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
     link: AgentLink<Self>,
 }
@@ -13,8 +16,10 @@ where
 impl<const N: usize> Agent for SolvingWorker<N>
 where
     Sudoku<N>: Serialize + DeserializeOwned,
+    SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
-    type Input = (Sudoku<N>, usize);
+    type Input = (SudokuDomains<N>, SudokuRegions<N>, Vec<Variant>, usize);
     type Message = ();
     type Output = (Option<Sudoku<N>>, usize);
     type Reach = Public<Self>;
@@ -26,8 +31,8 @@ where
     fn update(&mut self, _msg: Self::Message) {}
 
     fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
-        let (sudoku, change) = msg;
-        let prob = create_problem(&sudoku_domains(&sudoku));
+        let (domains, regions, variants, change) = msg;
+        let prob = create_problem(&domains, &regions, &variants);
         let result = prob.find_model().and_then(|v| Some(reshape_variables(v)));
         self.link.respond(id, (result, change));
     }
@@ -44,6 +49,7 @@ where
 pub struct MinimizingWorker<const N: usize>
 where
     SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
     link: AgentLink<Self>,
 }
@@ -51,8 +57,15 @@ where
 impl<const N: usize> Agent for MinimizingWorker<N>
 where
     SudokuDomains<N>: Serialize + DeserializeOwned,
+    SudokuRegions<N>: Serialize + DeserializeOwned,
 {
-    type Input = (SudokuDomains<N>, SudokuDomains<N>, usize);
+    type Input = (
+        SudokuDomains<N>,
+        SudokuDomains<N>,
+        SudokuRegions<N>,
+        Vec<Variant>,
+        usize,
+    );
     type Message = ();
     type Output = (SudokuDomains<N>, SudokuDomains<N>, usize);
     type Reach = Public<Self>;
@@ -64,8 +77,8 @@ where
     fn update(&mut self, _msg: Self::Message) {}
 
     fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
-        let (domains, unsure, change) = msg;
-        let prob = create_problem(&domains);
+        let (domains, unsure, regions, variants, change) = msg;
+        let prob = create_problem(&domains, &regions, &variants);
         let (result, unsure) = prob.minimize_domains_for(flatten_domains(unsure), 200);
         let result = reshape_domains(result);
         let unsure = reshape_domains(unsure);
@@ -80,3 +93,69 @@ where
         true
     }
 }
+
+pub struct MultiSolvingWorker<const N: usize> {
+    link: AgentLink<Self>,
+}
+
+impl<const N: usize> Agent for MultiSolvingWorker<N> {
+    type Input = (MultiLayout<N>, MultiDomains, usize);
+    type Message = ();
+    type Output = (Option<MultiSudoku>, usize);
+    type Reach = Public<Self>;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Self { link }
+    }
+
+    fn update(&mut self, _msg: Self::Message) {}
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        let (layout, domains, change) = msg;
+        let prob = layout.create_problem(&domains);
+        let result = prob.find_model().map(|v| layout.reshape_variables(v));
+        self.link.respond(id, (result, change));
+    }
+
+    fn name_of_resource() -> &'static str {
+        Box::leak(format!("worker_solve_multi{N}.js").into_boxed_str())
+    }
+
+    fn resource_path_is_relative() -> bool {
+        true
+    }
+}
+
+pub struct MultiMinimizingWorker<const N: usize> {
+    link: AgentLink<Self>,
+}
+
+impl<const N: usize> Agent for MultiMinimizingWorker<N> {
+    type Input = (MultiLayout<N>, MultiDomains, MultiDomains, usize);
+    type Message = ();
+    type Output = (MultiDomains, MultiDomains, usize);
+    type Reach = Public<Self>;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Self { link }
+    }
+
+    fn update(&mut self, _msg: Self::Message) {}
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        let (layout, domains, unsure, change) = msg;
+        let prob = layout.create_problem(&domains);
+        let (result, unsure) = prob.minimize_domains_for(layout.flatten_domains(&unsure), 200);
+        let result = layout.reshape_domains(result);
+        let unsure = layout.reshape_domains(unsure);
+        self.link.respond(id, (result, unsure, change));
+    }
+
+    fn name_of_resource() -> &'static str {
+        Box::leak(format!("worker_minimize_multi{N}.js").into_boxed_str())
+    }
+
+    fn resource_path_is_relative() -> bool {
+        true
+    }
+}